@@ -22,11 +22,11 @@ pub fn main() {
 
     let mut args = session.session_run_args();
 
-    let token = session.request_fetch(&mut args, &e).unwrap();
+    let fetch = session.request_typed_fetch(&mut args, &e).unwrap();
 
     session.run(&mut args).expect("Error running session!");
 
-    let output = args.fetch::<f32>(token);
+    let output = fetch.get(&mut args).expect("Failed to fetch output");
 
-    println!("got output: {:?}", output);
+    println!("got output: {:?}", output.values());
 }
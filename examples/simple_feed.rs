@@ -23,14 +23,14 @@ pub fn main() {
     let feed_tensor = feed_data.tag().unwrap();
 
     let mut args = session.session_run_args();
-    let token = session.request_fetch(&mut args, &e).unwrap();
+    let fetch = session.request_typed_fetch(&mut args, &e).unwrap();
     session
         .add_feed(&mut args, &v2.refer(), &feed_tensor)
         .expect("Failed to add feed");
 
     session.run(&mut args).expect("Error running session");
 
-    let output = args.fetch::<f32>(token);
+    let output = fetch.get(&mut args).expect("Failed to fetch output");
 
-    println!("got output: {:?}", output);
+    println!("got output: {:?}", output.values());
 }
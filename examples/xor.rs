@@ -7,11 +7,9 @@ const HIDDEN_SIZE: usize = 8;
 const TRAINING_ITERATIONS: usize = 100_000;
 
 pub fn main() {
-    let input_shape: [usize; 2] = [2, 1];
-    let label_shape: [usize; 2] = [1, 1];
-
-    let input = float_feed("input", &input_shape);
-    let label = float_feed("label", &label_shape);
+    // One column per sample, the batch size is only known when feeding.
+    let input = float_feed("input", [Dim::Fixed(2), Dim::Dynamic]);
+    let label = float_feed("label", [Dim::Fixed(1), Dim::Dynamic]);
 
     let weight1_shape: [usize; 2] = [HIDDEN_SIZE, 2];
     let weight2_shape: [usize; 2] = [1, HIDDEN_SIZE];
@@ -32,11 +30,11 @@ pub fn main() {
 
     let output = weight2.read().mat_mul(weight1.read().mat_mul(input.read()).tanh()).tanh();
     let error = output.clone() - label.read();
-    let error_sqr = error.clone() * error;
+    let loss = (error.clone() * error).reduce_sum_all();
 
     let mut compiler = Compiler::new_with_root_scope();
 
-    let min_error = error_sqr.clone().minimize(&[weight2.refer(), weight1.refer()]);
+    let min_error = loss.clone().minimize(&[weight2.refer(), weight1.refer()]);
 
     let _ = compiler.compile(&min_error).unwrap();
 
@@ -48,37 +46,24 @@ pub fn main() {
 
     println!("running training iterations");
 
-    for j in 0 .. TRAINING_ITERATIONS {
-        let mut error_sum: f32 = 0.;
-
-        for i in 0 .. 4 {
-            let a = i & 1;
-            let b = (i & 2) >> 1;
-            let l = a ^ b;
-
-            let input_feed = TensorData::<2, FloatData<2>>::new(&input_shape, &[a as f32, b as f32]);
-            let label_feed = TensorData::<2, FloatData<2>>::new(&label_shape, &[l as f32]);
-
-            let input_tensor = input_feed.tag().unwrap();
-            let label_tensor = label_feed.tag().unwrap();
-
-            let mut args = session.session_run_args();
+    let a: Vec<f32> = (0 .. 4).map(|i| (i & 1) as f32).collect();
+    let b: Vec<f32> = (0 .. 4).map(|i| ((i & 2) >> 1) as f32).collect();
+    let l: Vec<f32> = (0 .. 4).map(|i| ((i & 1) ^ ((i & 2) >> 1)) as f32).collect();
 
-            session.add_feed(&mut args, &input.refer(), &input_tensor).expect("Could not add input feed");
-            session.add_feed(&mut args, &label.refer(), &label_tensor).expect("Could not add label feed");
-            session.add_target(&mut args, &min_error);
-
-            let err_token = session.request_fetch(&mut args, &error_sqr).unwrap();
-
-            session.run(&mut args).expect("Error running training iteration");
-
-            let output = args.fetch::<f32>(err_token).unwrap();
+    for j in 0 .. TRAINING_ITERATIONS {
+        let input_feed = TensorData::<2, FloatData<2>>::new([2_usize, 4], &[a.clone(), b.clone()].concat());
+        let label_feed = TensorData::<2, FloatData<2>>::new([1_usize, 4], &l);
 
-            error_sum += output[0].sqrt();
-        }
+        let output = session
+            .eval_with_targets(
+                &[&min_error],
+                &loss,
+                feeds![input => input_feed, label => label_feed],
+            )
+            .expect("Error running training iteration");
 
         if j % 100 == 0 {
-            println!("Iteration {}. Error: {}", j, error_sum);
+            println!("Iteration {}. Error: {}", j, output.values()[0].sqrt());
         }
     }
 
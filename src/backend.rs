@@ -0,0 +1,85 @@
+use crate::compiler::{CompiledElement, Compiler};
+use crate::expr::ExprNode;
+use std::convert::Infallible;
+use tensorflow::Scope;
+use tensorflow::Status;
+
+/// Something expressions can be lowered to, like a TensorFlow graph. Backends lower one node at
+/// a time, described independently of any backend by `ExprNode::op`, so they can also be
+/// written outside this crate. A `Compiler<B>` memoizes the results by id, so every expression
+/// is lowered once per compiler.
+pub trait Backend: Sized {
+    /// What an expression lowers to, e.g. a TensorFlow operation.
+    type Element: Clone;
+    type Error;
+
+    /// Lowers `node`. Its inputs are lowered through `compiler`, so that they are memoized.
+    fn lower(
+        compiler: &mut Compiler<Self>,
+        node: &dyn ExprNode,
+    ) -> Result<Self::Element, Self::Error>;
+}
+
+/// The default backend, which builds a TensorFlow graph to run with a `RuntimeSession`.
+pub struct Tensorflow {
+    pub(crate) scope: Scope,
+}
+
+impl Tensorflow {
+    pub fn new(scope: Scope) -> Self {
+        Self { scope }
+    }
+}
+
+impl Backend for Tensorflow {
+    type Element = CompiledElement;
+    type Error = Status;
+
+    fn lower(
+        compiler: &mut Compiler<Self>,
+        node: &dyn ExprNode,
+    ) -> Result<CompiledElement, Status> {
+        node.make_operation(compiler)
+    }
+}
+
+/// A symbolic printer, which lowers every expression to a line of a listing like
+/// `%7 = mat_mul(%3, %5): [1, ?]`. Expressions lower to their names, like `%7`.
+#[derive(Default)]
+pub struct Printer {
+    lines: Vec<String>,
+}
+
+impl Printer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The lines of everything lowered so far, inputs before the expressions using them.
+    pub fn listing(&self) -> String {
+        self.lines.join("\n")
+    }
+}
+
+impl Backend for Printer {
+    type Element = String;
+    type Error = Infallible;
+
+    fn lower(compiler: &mut Compiler<Self>, node: &dyn ExprNode) -> Result<String, Infallible> {
+        let mut inputs = Vec::new();
+
+        for input in node.inputs() {
+            inputs.push(compiler.lower(input)?);
+        }
+
+        let symbol = format!("%{}", node.id());
+        let line = if inputs.is_empty() {
+            format!("{} = {} {}: {:?}", symbol, node.kind(), node.name(), node.dims())
+        } else {
+            format!("{} = {}({}): {:?}", symbol, node.name(), inputs.join(", "), node.dims())
+        };
+
+        compiler.backend.lines.push(line);
+        Ok(symbol)
+    }
+}
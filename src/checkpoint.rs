@@ -0,0 +1,93 @@
+use tensorflow::ops;
+use tensorflow::Code;
+use tensorflow::DataType;
+use tensorflow::Operation;
+use tensorflow::Output;
+use tensorflow::Scope;
+use tensorflow::Shape;
+use tensorflow::Status;
+use tensorflow::Tensor;
+use tensorflow::Variable;
+
+/// Save and restore operations for a set of variables, in the TensorFlow V2 checkpoint format.
+/// Variables are keyed by their name, so the names must be unique.
+#[derive(Clone)]
+pub(crate) struct Checkpoint {
+    pub(crate) prefix: Operation,
+    pub(crate) save: Operation,
+    pub(crate) restore: Operation,
+}
+
+impl Checkpoint {
+    pub(crate) fn build(scope: &mut Scope, variables: &[Variable]) -> Result<Self, Status> {
+        let mut sorted: Vec<&str> = variables.iter().map(|v| v.name()).collect();
+        sorted.sort_unstable();
+
+        if let Some(pair) = sorted.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(Status::new_set_lossy(
+                Code::InvalidArgument,
+                &format!("More than one variable is named {}", pair[0]),
+            ));
+        }
+
+        let count = variables.len() as u64;
+
+        let names: Vec<String> = variables.iter().map(|v| v.name().to_string()).collect();
+        let slices = vec![String::new(); variables.len()];
+        let data_types: Vec<DataType> = variables.iter().map(|v| v.data_type()).collect();
+        let outputs: Vec<Output> = variables.iter().map(|v| v.output().clone()).collect();
+
+        let prefix = ops::Placeholder::new()
+            .dtype(DataType::String)
+            .shape(Shape::from(Some(vec![])))
+            .build(&mut scope.with_op_name("checkpoint/prefix"))?;
+        let tensor_names = ops::constant(
+            Tensor::<String>::new(&[count]).with_values(&names)?,
+            &mut scope.with_op_name("checkpoint/tensor_names"),
+        )?;
+        let shape_and_slices = ops::constant(
+            Tensor::<String>::new(&[count]).with_values(&slices)?,
+            &mut scope.with_op_name("checkpoint/shape_and_slices"),
+        )?;
+
+        let save = {
+            let name = scope.get_unique_name_for_op("checkpoint/SaveV2");
+            let mut graph = scope.graph_mut();
+            let mut description = graph.new_operation("SaveV2", &name)?;
+
+            description.add_input(prefix.output(0));
+            description.add_input(tensor_names.output(0));
+            description.add_input(shape_and_slices.output(0));
+            description.add_input_list(&outputs);
+            description.set_attr_type_list("dtypes", &data_types)?;
+            description.finish()?
+        };
+
+        let restored = {
+            let name = scope.get_unique_name_for_op("checkpoint/RestoreV2");
+            let mut graph = scope.graph_mut();
+            let mut description = graph.new_operation("RestoreV2", &name)?;
+
+            description.add_input(prefix.output(0));
+            description.add_input(tensor_names.output(0));
+            description.add_input(shape_and_slices.output(0));
+            description.set_attr_type_list("dtypes", &data_types)?;
+            description.finish()?
+        };
+
+        let mut restore = ops::NoOp::new();
+
+        for (i, variable) in variables.iter().enumerate() {
+            let assign = ops::assign(variable.output().clone(), restored.output(i), scope)?;
+            restore = restore.add_control_input(assign);
+        }
+
+        let restore = restore.build(&mut scope.with_op_name("checkpoint/restore"))?;
+
+        Ok(Self {
+            prefix,
+            save,
+            restore,
+        })
+    }
+}
@@ -1,7 +1,10 @@
+use crate::backend::{Backend, Tensorflow};
 use crate::data::*;
 use crate::expr::VariableRef;
-use crate::expr::{Expr, Id};
+use crate::expr::{Expr, ExprNode, Id};
 use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 use tensorflow::Code;
 use tensorflow::Operation;
 use tensorflow::Output;
@@ -9,92 +12,145 @@ use tensorflow::Scope;
 use tensorflow::Status;
 use tensorflow::Variable;
 
-pub struct Compiler {
-    pub(crate) scope: Scope,
-    pub(crate) elements: HashMap<Id, CompiledElement>,
+/// Lowers expressions to a backend, by default to a TensorFlow graph. Every expression is
+/// lowered once, later uses get the memoized result.
+pub struct Compiler<B: Backend = Tensorflow> {
+    pub(crate) backend: B,
+    pub(crate) elements: HashMap<Id, B::Element>,
+    eliminate_common_subexpressions: bool,
+    /// The structures seen so far with the first expression of each, and the expression each
+    /// expression is shared with.
+    structures: HashMap<String, Id>,
+    canonical_ids: HashMap<Id, Id>,
 }
 
-impl Compiler {
-    pub fn new(scope: Scope) -> Self {
+impl<B: Backend> Compiler<B> {
+    pub fn with_backend(backend: B) -> Self {
         Self {
-            scope,
+            backend,
             elements: HashMap::new(),
+            eliminate_common_subexpressions: false,
+            structures: HashMap::new(),
+            canonical_ids: HashMap::new(),
         }
     }
 
-    pub fn new_with_root_scope() -> Self {
-        Self {
-            scope: Scope::new_root_scope(),
-            elements: HashMap::new(),
+    /// Enables common subexpression elimination: pure expressions with the same operation, the
+    /// same inputs and the same constant data are lowered once, even if they were built
+    /// separately, e.g. by calling the same helper function twice.
+    pub fn with_common_subexpression_elimination(mut self) -> Self {
+        self.eliminate_common_subexpressions = true;
+        self
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Lowers `node` to the backend, or gets the result of an earlier lowering.
+    pub fn lower(&mut self, node: &dyn ExprNode) -> Result<B::Element, B::Error> {
+        let id = node.id();
+
+        if let Some(element) = self.elements.get(&id) {
+            return Ok(element.clone());
         }
+
+        // A shared expression is also recorded under its own id, so that it can be fetched.
+        let canonical_id = self.canonical_id(node);
+        let element = match self.elements.get(&canonical_id) {
+            Some(element) => element.clone(),
+            None => B::lower(self, node)?,
+        };
+
+        self.elements.insert(canonical_id, element.clone());
+        self.elements.insert(id, element.clone());
+        Ok(element)
     }
 
-    pub fn borrow_scope_mut(&mut self) -> &mut Scope {
-        &mut self.scope
+    /// The id of the first structurally identical expression with common subexpression
+    /// elimination, otherwise the id of `node` itself.
+    fn canonical_id(&mut self, node: &dyn ExprNode) -> Id {
+        if !self.eliminate_common_subexpressions {
+            return node.id();
+        }
+
+        if let Some(id) = self.canonical_ids.get(&node.id()) {
+            return *id;
+        }
+
+        let id = match node.structural_key() {
+            Some(key) => {
+                let inputs: Vec<Id> =
+                    node.inputs().into_iter().map(|input| self.canonical_id(input)).collect();
+                let structure = format!("{} {:?}", key, inputs);
+
+                *self.structures.entry(structure).or_insert(node.id())
+            }
+            None => node.id(),
+        };
+
+        self.canonical_ids.insert(node.id(), id);
+        id
     }
 
     pub fn compile<const RANK: usize, D: Data<RANK>>(
         &mut self,
         expr: &Expr<RANK, D>,
-    ) -> Result<(), Status> {
-        let id = expr.0.id();
+    ) -> Result<(), B::Error> {
+        self.lower(expr).map(|_| ())
+    }
+}
 
-        match self.elements.get(&id) {
-            Some(_) => Ok(()),
-            None => {
-                let element = expr.0.make_operation(self)?;
-                self.elements.insert(id, element);
-                Ok(())
-            }
-        }
+impl<B: Backend + Default> Default for Compiler<B> {
+    fn default() -> Self {
+        Self::with_backend(B::default())
+    }
+}
+
+impl Compiler {
+    pub fn new(scope: Scope) -> Self {
+        Self::with_backend(Tensorflow::new(scope))
+    }
+
+    pub fn new_with_root_scope() -> Self {
+        Self::new(Scope::new_root_scope())
+    }
+
+    pub fn borrow_scope_mut(&mut self) -> &mut Scope {
+        &mut self.backend.scope
+    }
+
+    /// The serialized `GraphDef` of everything compiled so far.
+    pub fn to_graph_def(&self) -> Result<Vec<u8>, Status> {
+        self.backend.scope.graph().graph_def()
+    }
+
+    /// Writes the serialized `GraphDef` to a file, e.g. for viewing in TensorBoard or Netron.
+    pub fn write_graph<P: AsRef<Path>>(&self, path: P) -> Result<(), Status> {
+        let graph_def = self.to_graph_def()?;
+
+        fs::write(path, graph_def).map_err(|e| {
+            Status::new_set_lossy(Code::Internal, &format!("Failed to write graph: {}", e))
+        })
     }
 
     pub fn get_output<const RANK: usize, D: Data<RANK>>(
         &mut self,
         expr: &Expr<RANK, D>,
     ) -> Result<Output, Status> {
-        let id = expr.0.id();
-
-        match self.elements.get(&id) {
-            Some(CompiledElement::Operation(operation)) => Ok(operation.output(0)),
-            Some(CompiledElement::Variable(variable)) => Ok(variable.output().clone()),
-            Some(CompiledElement::Optimizer(_, _)) => Err(Status::new_set_lossy(
-                Code::InvalidArgument,
-                "You can't use the output from an optimizer yet",
-            )),
-            None => {
-                let element = expr.0.make_operation(self)?;
-                let output = element.output()?;
-                self.elements.insert(id, element);
-                Ok(output)
-            }
-        }
+        self.lower(expr)?.output()
     }
 
     pub fn get_operation<const RANK: usize, D: Data<RANK>>(
         &mut self,
         expr: &Expr<RANK, D>,
     ) -> Result<Operation, Status> {
-        let id = expr.0.id();
-
-        match self.elements.get(&id) {
-            Some(CompiledElement::Operation(operation)) => Ok(operation.clone()),
-            Some(_) => Err(Status::new_set_lossy(
+        match self.lower(expr)? {
+            CompiledElement::Operation(operation) => Ok(operation),
+            _ => Err(Status::new_set_lossy(
                 Code::InvalidArgument,
                 "Expression is not an operation",
             )),
-            None => {
-                let element = expr.0.make_operation(self)?;
-                self.elements.insert(id, element.clone());
-
-                match element {
-                    CompiledElement::Operation(operation) => Ok(operation),
-                    _ => Err(Status::new_set_lossy(
-                        Code::InvalidArgument,
-                        "Expression is not an operation",
-                    )),
-                }
-            }
         }
     }
 
@@ -102,26 +158,12 @@ impl Compiler {
         &mut self,
         expr: &Expr<RANK, D>,
     ) -> Result<Variable, Status> {
-        let id = expr.0.id();
-
-        match self.elements.get(&id) {
-            Some(CompiledElement::Variable(variable)) => Ok(variable.clone()),
-            Some(_) => Err(Status::new_set_lossy(
+        match self.lower(expr)? {
+            CompiledElement::Variable(variable) => Ok(variable),
+            _ => Err(Status::new_set_lossy(
                 Code::InvalidArgument,
                 "Expression is not a variable",
             )),
-            None => {
-                let operation = expr.0.make_operation(self)?;
-                self.elements.insert(id, operation.clone());
-
-                match operation {
-                    CompiledElement::Variable(variable) => Ok(variable),
-                    _ => Err(Status::new_set_lossy(
-                        Code::InvalidArgument,
-                        "Expression is not an operation",
-                    )),
-                }
-            }
         }
     }
 
@@ -138,12 +180,14 @@ impl Compiler {
     }
 }
 
+/// A TensorFlow operation or variable an expression was lowered to.
 #[derive(Clone)]
-pub(crate) enum CompiledElement {
+pub enum CompiledElement {
     Operation(Operation),
     Variable(Variable),
     #[allow(dead_code)]
     Optimizer(Operation, Vec<Variable>),
+    Gradients(Vec<Option<Output>>),
 }
 
 impl CompiledElement {
@@ -155,6 +199,113 @@ impl CompiledElement {
                 Code::InvalidArgument,
                 "Cannot use the output of an optimizer",
             )),
+            CompiledElement::Gradients(_) => Err(Status::new_set_lossy(
+                Code::InvalidArgument,
+                "Cannot use the gradients directly",
+            )),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Printer;
+    use crate::expr::{float_feed, float_variable, random_uniform, scalar, vector};
+
+    fn compiler() -> Compiler<Printer> {
+        Compiler::with_backend(Printer::new()).with_common_subexpression_elimination()
+    }
+
+    fn lower<const RANK: usize, D: Data<RANK>>(
+        compiler: &mut Compiler<Printer>,
+        expr: &Expr<RANK, D>,
+    ) -> String {
+        compiler.lower(expr).unwrap()
+    }
+
+    #[test]
+    fn shares_identical_expressions() {
+        let x = float_feed("x", [2_usize]).read();
+        let y = float_feed("y", [2_usize]).read();
+        let mut compiler = compiler();
+
+        let first = lower(&mut compiler, &(x.clone() + y.clone()));
+        let second = lower(&mut compiler, &(x + y));
+
+        assert_eq!(first, second);
+        assert_eq!(compiler.backend().listing().lines().count(), 3);
+    }
+
+    #[test]
+    fn keeps_identical_expressions_without_elimination() {
+        let x = float_feed("x", [2_usize]).read();
+        let y = float_feed("y", [2_usize]).read();
+        let mut compiler = Compiler::with_backend(Printer::new());
+
+        let first = lower(&mut compiler, &(x.clone() + y.clone()));
+        let second = lower(&mut compiler, &(x + y));
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn shares_equal_constants() {
+        let mut compiler = compiler();
+
+        let first = lower(&mut compiler, &scalar::<FloatData<0>, _>(2.0_f32));
+        let second = lower(&mut compiler, &scalar::<FloatData<0>, _>(2.0_f32));
+        let other = lower(&mut compiler, &scalar::<FloatData<0>, _>(3.0_f32));
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn keeps_int64_constants_apart_beyond_f64_precision() {
+        let mut compiler = compiler();
+
+        let first = lower(&mut compiler, &vector::<Int64Data<1>>(&[1 << 53]));
+        let second = lower(&mut compiler, &vector::<Int64Data<1>>(&[(1 << 53) + 1]));
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn never_shares_placeholders() {
+        let mut compiler = compiler();
+
+        let first = lower(&mut compiler, &float_feed("x", [2_usize]).read());
+        let second = lower(&mut compiler, &float_feed("x", [2_usize]).read());
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn never_shares_variables() {
+        let mut compiler = compiler();
+
+        let first = float_variable("w", vector(&[0.0_f32]), [1_usize]);
+        let second = float_variable("w", vector(&[0.0_f32]), [1_usize]);
+
+        assert_ne!(
+            lower(&mut compiler, &first.read()),
+            lower(&mut compiler, &second.read())
+        );
+    }
+
+    #[test]
+    fn never_shares_random_values() {
+        let mut compiler = compiler();
+
+        let first = random_uniform([2_usize]);
+        let second = random_uniform([2_usize]);
+        let two = || scalar::<FloatData<0>, _>(2.0_f32);
+
+        assert_ne!(lower(&mut compiler, &first), lower(&mut compiler, &second));
+        assert_ne!(
+            lower(&mut compiler, &(first * two())),
+            lower(&mut compiler, &(second * two()))
+        );
+    }
+}
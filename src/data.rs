@@ -1,36 +1,148 @@
+use std::fmt;
 use std::marker::PhantomData;
 use tensorflow::DataType;
 use tensorflow::Shape;
 use tensorflow::TensorType;
 
+/// A dimension of a shape, either known when the graph is built or only when it runs, like a
+/// batch axis.
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Dim {
+    Dynamic,
+    Fixed(usize),
+}
+
+impl Dim {
+    pub fn size(&self) -> Option<usize> {
+        match self {
+            Dim::Dynamic => None,
+            Dim::Fixed(size) => Some(*size),
+        }
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        *self == Dim::Dynamic
+    }
+
+    /// Whether the dimensions can be equal at run time.
+    pub fn is_compatible_with(&self, other: &Dim) -> bool {
+        match (self, other) {
+            (Dim::Fixed(a), Dim::Fixed(b)) => a == b,
+            _ => true,
+        }
+    }
+}
+
+impl From<usize> for Dim {
+    fn from(size: usize) -> Self {
+        Dim::Fixed(size)
+    }
+}
+
+/// Fixed dimensions are written as their size, dynamic dimensions as `?`.
+impl fmt::Debug for Dim {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Dim::Dynamic => write!(f, "?"),
+            Dim::Fixed(size) => write!(f, "{}", size),
+        }
+    }
+}
+
+/// Element types of tensors. The `f64` conversions are used by the reference `Evaluator`,
+/// which computes in `f64`.
+pub trait ElementType: TensorType {
+    fn to_f64(&self) -> f64;
+    fn from_f64(value: f64) -> Self;
+}
+
+macro_rules! numeric_element_type {
+    ($($element:ty),*) => {
+        $(
+            impl ElementType for $element {
+                fn to_f64(&self) -> f64 {
+                    *self as f64
+                }
+
+                fn from_f64(value: f64) -> Self {
+                    value as $element
+                }
+            }
+        )*
+    };
+}
+
+numeric_element_type!(f32, f64, i32, i64, u8);
+
+impl ElementType for bool {
+    fn to_f64(&self) -> f64 {
+        if *self {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value != 0.0
+    }
+}
+
 pub trait Data<const RANK: usize>: Clone {
-    type Element: TensorType;
+    type Element: ElementType;
 
     fn rank(&self) -> usize {
         RANK
     }
 
     fn data_type(&self) -> DataType;
-    fn shape(&self) -> Shape;
-    fn dimensions(&self) -> Vec<u64>;
 
-    fn dimensions_i64(&self) -> Vec<i64> {
-        let dimensions = self.dimensions();
+    /// The dimensions, some of which may be dynamic.
+    fn dims(&self) -> Vec<Dim>;
 
-        let mut result = Vec::new();
+    /// The same data type with the dynamic dimensions taken from `dimensions`, e.g. those of a
+    /// fetched tensor.
+    fn with_known_dimensions(&self, dimensions: &[u64]) -> Self;
 
-        for i in 0 .. RANK {
-            result.push(dimensions[i] as i64);
-        }
+    /// The shape, with unknown sizes for dynamic dimensions.
+    fn shape(&self) -> Shape {
+        let dims: Vec<Option<i64>> = self
+            .dims()
+            .iter()
+            .map(|d| d.size().map(|s| s as i64))
+            .collect();
+
+        Shape::from(Some(dims))
+    }
+
+    fn is_fully_defined(&self) -> bool {
+        self.dims().iter().all(|d| !d.is_dynamic())
+    }
 
-        result
+    /// The dimensions of a fully defined data type, e.g. of a tensor value. Panics if a
+    /// dimension is dynamic.
+    fn dimensions(&self) -> Vec<u64> {
+        let dims = self.dims();
+
+        dims.iter()
+            .map(|d| match d.size() {
+                Some(size) => size as u64,
+                None => panic!("{:?} has dynamic dimensions", dims),
+            })
+            .collect()
+    }
+
+    /// The dimensions with dynamic dimensions as -1, as used by `ops::reshape`.
+    fn dimensions_i64(&self) -> Vec<i64> {
+        self.dims()
+            .iter()
+            .map(|d| d.size().map_or(-1, |s| s as i64))
+            .collect()
     }
 }
 
 pub trait ScalarData: Data<0> + Clone {}
 
-impl ScalarData for FloatData<0> {}
-
 #[derive(PartialEq, Clone)]
 pub struct NoData {
     phantom: PhantomData<usize>,
@@ -51,18 +163,13 @@ impl Data<0> for NoData {
         DataType::Int32
     }
 
-    fn shape(&self) -> Shape {
-        [0; 0].into()
-    }
-
-    fn dimensions(&self) -> Vec<u64> {
+    fn dims(&self) -> Vec<Dim> {
         Vec::new()
     }
-}
 
-#[derive(PartialEq, Clone)]
-pub struct FloatData<const D: usize> {
-    shape: [usize; D],
+    fn with_known_dimensions(&self, _dimensions: &[u64]) -> Self {
+        self.clone()
+    }
 }
 
 /*
@@ -83,101 +190,284 @@ impl<const D: usize> From<&[usize]> for FloatData<D> {
 }
 */
 
-impl<const RANK: usize> From<[usize; RANK]> for FloatData<RANK> {
-    fn from(shape: [usize; RANK]) -> Self {
-        FloatData { shape }
-    }
+/// Data types that exist at every rank, so that an expression can change rank (reductions,
+/// reshapes) while keeping its element type.
+pub trait RankedData<const RANK: usize>:
+    Data<RANK> + From<[usize; RANK]> + From<[Dim; RANK]> + 'static
+{
+    type WithRank<const N: usize>: RankedData<N, Element = Self::Element>;
+
+    fn with_dimensions<const N: usize>(dimensions: [Dim; N]) -> Self::WithRank<N>;
 }
 
-impl<const RANK: usize> From<&[usize; RANK]> for FloatData<RANK> {
-    fn from(shape: &[usize; RANK]) -> Self {
-        FloatData {
-            shape: shape.clone(),
+/// Marker for data types that support arithmetic (`+`, `-`, `*`, `/`).
+pub trait NumericData<const RANK: usize>: Data<RANK> {}
+
+/// Marker for floating point data types, which support the transcendental functions.
+pub trait FloatingData<const RANK: usize>: NumericData<RANK> {}
+
+macro_rules! tensor_data_type {
+    ($(#[$meta:meta])* $name:ident, $element:ty, $data_type:expr) => {
+        $(#[$meta])*
+        #[derive(PartialEq, Clone)]
+        pub struct $name<const RANK: usize> {
+            shape: [Dim; RANK],
         }
-    }
-}
 
-impl<const RANK: usize> From<[u64; RANK]> for FloatData<RANK> {
-    fn from(shape_u64: [u64; RANK]) -> Self {
-        let mut shape: [usize; RANK] = [0; RANK];
+        impl<const RANK: usize> From<[Dim; RANK]> for $name<RANK> {
+            fn from(shape: [Dim; RANK]) -> Self {
+                $name { shape }
+            }
+        }
 
-        for i in 0 .. RANK {
-            shape[i] = shape_u64[i] as usize;
+        impl<const RANK: usize> From<&[Dim; RANK]> for $name<RANK> {
+            fn from(shape: &[Dim; RANK]) -> Self {
+                $name { shape: *shape }
+            }
         }
 
-        FloatData { shape }
-    }
-}
+        impl<const RANK: usize> From<[usize; RANK]> for $name<RANK> {
+            fn from(shape: [usize; RANK]) -> Self {
+                $name {
+                    shape: shape.map(Dim::Fixed),
+                }
+            }
+        }
+
+        impl<const RANK: usize> From<&[usize; RANK]> for $name<RANK> {
+            fn from(shape: &[usize; RANK]) -> Self {
+                $name {
+                    shape: shape.map(Dim::Fixed),
+                }
+            }
+        }
 
-impl<const RANK: usize> From<&[u64; RANK]> for FloatData<RANK> {
-    fn from(shape_u64: &[u64; RANK]) -> Self {
-        let mut shape: [usize; RANK] = [0; RANK];
+        impl<const RANK: usize> From<[u64; RANK]> for $name<RANK> {
+            fn from(shape_u64: [u64; RANK]) -> Self {
+                $name {
+                    shape: shape_u64.map(|d| Dim::Fixed(d as usize)),
+                }
+            }
+        }
 
-        for i in 0 .. RANK {
-            shape[i] = shape_u64[i] as usize;
+        impl<const RANK: usize> From<&[u64; RANK]> for $name<RANK> {
+            fn from(shape_u64: &[u64; RANK]) -> Self {
+                $name {
+                    shape: shape_u64.map(|d| Dim::Fixed(d as usize)),
+                }
+            }
         }
 
-        FloatData { shape }
-    }
-}
+        impl<const RANK: usize> Data<RANK> for $name<RANK> {
+            type Element = $element;
 
-impl<const RANK: usize> Data<RANK> for FloatData<RANK> {
-    type Element = f32;
+            fn data_type(&self) -> DataType {
+                $data_type
+            }
 
-    fn data_type(&self) -> DataType {
-        DataType::Float
-    }
+            fn dims(&self) -> Vec<Dim> {
+                self.shape.to_vec()
+            }
 
-    fn shape(&self) -> Shape {
-        let mut shape_u64: Vec<u64> = Vec::new();
+            fn with_known_dimensions(&self, dimensions: &[u64]) -> Self {
+                let mut shape = self.shape;
 
-        for i in 0..self.shape.len() {
-            shape_u64.push(self.shape[i] as u64);
+                for (dim, known) in shape.iter_mut().zip(dimensions) {
+                    if dim.is_dynamic() {
+                        *dim = Dim::Fixed(*known as usize);
+                    }
+                }
+
+                $name { shape }
+            }
         }
 
-        shape_u64[..].into()
-    }
+        impl<const RANK: usize> RankedData<RANK> for $name<RANK> {
+            type WithRank<const N: usize> = $name<N>;
 
-    fn dimensions(&self) -> Vec<u64> {
-        let mut shape_u64: Vec<u64> = Vec::new();
+            fn with_dimensions<const N: usize>(dimensions: [Dim; N]) -> $name<N> {
+                $name { shape: dimensions }
+            }
+        }
 
-        for i in 0..self.shape.len() {
-            shape_u64.push(self.shape[i] as u64);
+        impl ScalarData for $name<0> {}
+    };
+}
+
+macro_rules! numeric_data_type {
+    ($(#[$meta:meta])* $name:ident, $element:ty, $data_type:expr) => {
+        tensor_data_type!($(#[$meta])* $name, $element, $data_type);
+
+        impl<const RANK: usize> NumericData<RANK> for $name<RANK> {}
+    };
+}
+
+numeric_data_type!(
+    /// Tensors of `f32`.
+    FloatData, f32, DataType::Float
+);
+numeric_data_type!(
+    /// Tensors of `f64`.
+    DoubleData, f64, DataType::Double
+);
+numeric_data_type!(
+    /// Tensors of `i32`.
+    Int32Data, i32, DataType::Int32
+);
+numeric_data_type!(
+    /// Tensors of `i64`.
+    Int64Data, i64, DataType::Int64
+);
+numeric_data_type!(
+    /// Tensors of `u8`.
+    UInt8Data, u8, DataType::UInt8
+);
+tensor_data_type!(
+    /// Tensors of `bool`. Typically used for masks; no arithmetic is defined on these.
+    BoolData, bool, DataType::Bool
+);
+
+impl<const RANK: usize> FloatingData<RANK> for FloatData<RANK> {}
+impl<const RANK: usize> FloatingData<RANK> for DoubleData<RANK> {}
+
+/// Shapes whose dimensions are part of the type, see `StaticData`.
+pub trait StaticShape<const RANK: usize>: Clone + 'static {
+    const DIMS: [usize; RANK];
+}
+
+#[derive(PartialEq, Clone, Copy, Default)]
+pub struct Shape0;
+
+#[derive(PartialEq, Clone, Copy, Default)]
+pub struct Shape1<const D0: usize>;
+
+#[derive(PartialEq, Clone, Copy, Default)]
+pub struct Shape2<const D0: usize, const D1: usize>;
+
+#[derive(PartialEq, Clone, Copy, Default)]
+pub struct Shape3<const D0: usize, const D1: usize, const D2: usize>;
+
+#[derive(PartialEq, Clone, Copy, Default)]
+pub struct Shape4<const D0: usize, const D1: usize, const D2: usize, const D3: usize>;
+
+impl StaticShape<0> for Shape0 {
+    const DIMS: [usize; 0] = [];
+}
+
+impl<const D0: usize> StaticShape<1> for Shape1<D0> {
+    const DIMS: [usize; 1] = [D0];
+}
+
+impl<const D0: usize, const D1: usize> StaticShape<2> for Shape2<D0, D1> {
+    const DIMS: [usize; 2] = [D0, D1];
+}
+
+impl<const D0: usize, const D1: usize, const D2: usize> StaticShape<3> for Shape3<D0, D1, D2> {
+    const DIMS: [usize; 3] = [D0, D1, D2];
+}
+
+impl<const D0: usize, const D1: usize, const D2: usize, const D3: usize> StaticShape<4>
+    for Shape4<D0, D1, D2, D3>
+{
+    const DIMS: [usize; 4] = [D0, D1, D2, D3];
+}
+
+/// Tensors of `T` whose dimensions are checked by the Rust compiler, e.g. `Float<Shape2<3, 4>>`.
+/// Operations on these only compile when the dimensions fit, so there are no shape errors at
+/// run time. Use `Expr::into_dynamic` to get the runtime checked equivalent.
+pub struct StaticData<T, S> {
+    phantom: PhantomData<(T, S)>,
+}
+
+pub type Float<S> = StaticData<f32, S>;
+pub type Double<S> = StaticData<f64, S>;
+
+impl<T, S> StaticData<T, S> {
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
         }
+    }
+}
 
-        shape_u64
+impl<T, S> Default for StaticData<T, S> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-#[derive(PartialEq, Clone)]
-pub struct DoubleData<const RANK: usize> {
-    shape: [usize; RANK],
+impl<T, S> Clone for StaticData<T, S> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<T, S> PartialEq for StaticData<T, S> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
 }
 
-impl<const RANK: usize> Data<RANK> for DoubleData<RANK> {
-    type Element = f64;
+impl<T: ElementType, const RANK: usize, S: StaticShape<RANK>> Data<RANK> for StaticData<T, S> {
+    type Element = T;
 
     fn data_type(&self) -> DataType {
-        DataType::Double
+        T::data_type()
     }
 
-    fn shape(&self) -> Shape {
-        let mut shape_u64: Vec<u64> = Vec::new();
+    fn dims(&self) -> Vec<Dim> {
+        S::DIMS.iter().map(|d| Dim::Fixed(*d)).collect()
+    }
 
-        for i in 0..self.shape.len() {
-            shape_u64.push(self.shape[i] as u64);
-        }
+    fn with_known_dimensions(&self, _dimensions: &[u64]) -> Self {
+        self.clone()
+    }
+}
+
+impl<T: ElementType> ScalarData for StaticData<T, Shape0> {}
+
+macro_rules! static_numeric_data {
+    ($($element:ty),*) => {
+        $(
+            impl<const RANK: usize, S: StaticShape<RANK>> NumericData<RANK>
+                for StaticData<$element, S>
+            {
+            }
+        )*
+    };
+}
 
-        shape_u64[..].into()
+static_numeric_data!(f32, f64, i32, i64, u8);
+
+impl<const RANK: usize, S: StaticShape<RANK>> FloatingData<RANK> for StaticData<f32, S> {}
+impl<const RANK: usize, S: StaticShape<RANK>> FloatingData<RANK> for StaticData<f64, S> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_known_dimensions_resolves_dynamic_dimensions() {
+        let data_type = FloatData::from([Dim::Dynamic, Dim::Fixed(2)]);
+        let known = data_type.with_known_dimensions(&[5, 2]);
+
+        assert_eq!(known.dims(), vec![Dim::Fixed(5), Dim::Fixed(2)]);
+        assert!(known.is_fully_defined());
+        assert!(!data_type.is_fully_defined());
     }
 
-    fn dimensions(&self) -> Vec<u64> {
-        let mut shape_u64: Vec<u64> = Vec::new();
+    #[test]
+    fn with_known_dimensions_keeps_fixed_dimensions() {
+        let data_type = Int64Data::from([3_usize, 2]);
 
-        for i in 0..self.shape.len() {
-            shape_u64.push(self.shape[i] as u64);
-        }
+        assert_eq!(data_type.with_known_dimensions(&[3, 2]).dims(), data_type.dims());
+    }
 
-        shape_u64
+    #[test]
+    fn dynamic_dimensions_are_compatible() {
+        assert!(Dim::Dynamic.is_compatible_with(&Dim::Fixed(3)));
+        assert!(Dim::Fixed(3).is_compatible_with(&Dim::Dynamic));
+        assert!(Dim::Fixed(3).is_compatible_with(&Dim::Fixed(3)));
+        assert!(!Dim::Fixed(3).is_compatible_with(&Dim::Fixed(2)));
     }
 }
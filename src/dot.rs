@@ -0,0 +1,40 @@
+use crate::expr::{walk, ExprNode, ExprVisitor};
+
+/// Renders the expression tree below `root` as a Graphviz DOT graph. Every node shows its kind,
+/// operation, dimensions and id. Shared subexpressions are drawn once.
+pub fn to_dot(root: &dyn ExprNode) -> String {
+    let mut writer = DotWriter {
+        output: String::from("digraph expr {\n    node [shape=box];\n"),
+    };
+
+    walk(root, &mut writer);
+
+    writer.output.push_str("}\n");
+    writer.output
+}
+
+struct DotWriter {
+    output: String,
+}
+
+impl ExprVisitor for DotWriter {
+    fn visit(&mut self, node: &dyn ExprNode) {
+        self.output.push_str(&format!(
+            "    n{} [label=\"{}: {}\\n{:?}\\nid {}\"];\n",
+            node.id(),
+            node.kind(),
+            escape(&node.name()),
+            node.dims(),
+            node.id()
+        ));
+
+        for input in node.inputs() {
+            self.output
+                .push_str(&format!("    n{} -> n{};\n", input.id(), node.id()));
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
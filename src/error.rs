@@ -0,0 +1,77 @@
+use crate::data::Dim;
+use std::error::Error;
+use std::fmt;
+
+/// Raised when the operands of an expression have incompatible dimensions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapeError {
+    operation: &'static str,
+    left: Vec<Dim>,
+    right: Vec<Dim>,
+}
+
+impl ShapeError {
+    pub(crate) fn new(operation: &'static str, left: Vec<Dim>, right: Vec<Dim>) -> Self {
+        Self {
+            operation,
+            left,
+            right,
+        }
+    }
+
+    pub fn operation(&self) -> &str {
+        self.operation
+    }
+
+    pub fn left(&self) -> &[Dim] {
+        &self.left
+    }
+
+    pub fn right(&self) -> &[Dim] {
+        &self.right
+    }
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: incompatible shapes {:?} and {:?}",
+            self.operation, self.left, self.right
+        )
+    }
+}
+
+impl Error for ShapeError {}
+
+/// Raised by the reference `Evaluator`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    /// The expression contains an operation the evaluator can't compute, e.g. random numbers or
+    /// gradients.
+    Unsupported(String),
+    /// A placeholder was not fed.
+    MissingFeed(String),
+    /// Dimensions that were only known at run time turned out to be incompatible.
+    Shape(ShapeError),
+}
+
+impl From<ShapeError> for EvalError {
+    fn from(error: ShapeError) -> Self {
+        EvalError::Shape(error)
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::Unsupported(operation) => {
+                write!(f, "{} is not supported by the evaluator", operation)
+            }
+            EvalError::MissingFeed(name) => write!(f, "placeholder {} was not fed", name),
+            EvalError::Shape(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for EvalError {}
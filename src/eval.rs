@@ -0,0 +1,657 @@
+use crate::backend::Backend;
+use crate::compiler::Compiler;
+use crate::data::*;
+use crate::error::{EvalError, ShapeError};
+use crate::expr::{Expr, ExprNode, Id, Op, Placeholder, Variable};
+use crate::tensordata::TensorData;
+use std::collections::HashMap;
+use tensorflow::DataType;
+
+// TODO: put the TensorFlow backend behind an optional cargo feature, so that the evaluator and
+// the printer build without libtensorflow.
+
+/// A reference interpreter that computes expressions on the CPU in pure Rust, without a
+/// TensorFlow session. Values are computed in `f64` and converted to the element type of the
+/// expression at the end, so results can differ from TensorFlow in the last bits.
+///
+/// Integers follow `f64` arithmetic rather than that of TensorFlow: `i64` values beyond 2^53
+/// lose precision, results out of range saturate where TensorFlow wraps (e.g. for `u8`), and
+/// integer division by zero saturates where TensorFlow fails.
+///
+/// Random numbers, gradients, optimizers and imported graphs are not supported; set the
+/// variables initialized with random values with `set_variable`.
+///
+/// The evaluator needs no session, but it does not remove the dependency on libtensorflow yet:
+/// data types and expressions are still defined in terms of the `tensorflow` crate, so the
+/// crate links against it either way.
+pub struct Evaluator {
+    compiler: Compiler<Interpreter>,
+}
+
+/// The backend of the `Evaluator`, which lowers expressions to their values.
+#[derive(Default)]
+pub struct Interpreter {
+    inputs: HashMap<Id, Value>,
+}
+
+/// A tensor as seen by the evaluator.
+#[derive(Clone)]
+pub struct Value {
+    pub(crate) dims: Vec<usize>,
+    pub(crate) values: Vec<f64>,
+}
+
+impl Evaluator {
+    pub fn new() -> Self {
+        Self {
+            compiler: Compiler::with_backend(Interpreter::default()),
+        }
+    }
+
+    /// Feeds `value` to `placeholder` for all following evaluations.
+    pub fn feed<const RANK: usize, D: Data<RANK> + 'static, T: Into<TensorData<RANK, D>>>(
+        &mut self,
+        placeholder: &Placeholder<RANK, D>,
+        value: T,
+    ) -> Result<(), EvalError> {
+        let value = value.into();
+        check_dimensions("feed", &placeholder.data_type.dims(), &value)?;
+        self.set_input(placeholder.id, &value);
+        Ok(())
+    }
+
+    /// Overrides the value of `variable`, which otherwise is its initial value.
+    pub fn set_variable<
+        const RANK: usize,
+        D: Data<RANK> + 'static,
+        T: Into<TensorData<RANK, D>>,
+    >(
+        &mut self,
+        variable: &Variable<RANK, D>,
+        value: T,
+    ) -> Result<(), EvalError> {
+        let value = value.into();
+        check_dimensions("set_variable", &variable.data_type.dims(), &value)?;
+        self.set_input(variable.id, &value);
+        Ok(())
+    }
+
+    /// Computes the value of `expr`. Dynamic dimensions of the result take their computed sizes.
+    pub fn eval<const RANK: usize, D: Data<RANK> + 'static>(
+        &mut self,
+        expr: &Expr<RANK, D>,
+    ) -> Result<TensorData<RANK, D>, EvalError> {
+        let value = self.compiler.lower(expr)?;
+        let dimensions: Vec<u64> = value.dims.iter().map(|d| *d as u64).collect();
+
+        Ok(TensorData {
+            data: Some(value.values.iter().map(|v| D::Element::from_f64(*v)).collect()),
+            data_type: expr.0.data_type().with_known_dimensions(&dimensions),
+        })
+    }
+
+    /// Sets a placeholder or variable. Everything computed so far may depend on it.
+    fn set_input<const RANK: usize, D: Data<RANK> + 'static>(
+        &mut self,
+        id: Id,
+        value: &TensorData<RANK, D>,
+    ) {
+        self.compiler.elements.clear();
+        self.compiler.backend.inputs.insert(id, Value::from_tensor_data(value));
+    }
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Backend for Interpreter {
+    type Element = Value;
+    type Error = EvalError;
+
+    fn lower(compiler: &mut Compiler<Self>, node: &dyn ExprNode) -> Result<Value, EvalError> {
+        if let Some(value) = compiler.backend.inputs.get(&node.id()) {
+            return Ok(value.clone());
+        }
+
+        let integer = !matches!(node.data_type(), DataType::Float | DataType::Double);
+        let mut input = |i: usize| compiler.lower(node.inputs()[i]);
+
+        let value = match node.op() {
+            Op::Placeholder(name) => return Err(EvalError::MissingFeed(name)),
+            Op::Variable(_) | Op::Identity => input(0)?,
+            Op::Constant(values) => Value {
+                dims: node.dims().iter().filter_map(Dim::size).collect(),
+                values,
+            },
+            Op::Imported(_)
+            | Op::RandomUniform
+            | Op::RandomStandardNormal
+            | Op::Gradients
+            | Op::Gradient(_)
+            | Op::Minimize => return Err(EvalError::Unsupported(node.name())),
+            Op::Add => Value::broadcast("add", &input(0)?, &input(1)?, |a, b| a + b)?,
+            Op::Sub => Value::broadcast("sub", &input(0)?, &input(1)?, |a, b| a - b)?,
+            Op::Mul => Value::broadcast("mul", &input(0)?, &input(1)?, |a, b| a * b)?,
+            Op::Div if integer => {
+                Value::broadcast("div", &input(0)?, &input(1)?, |a, b| (a / b).trunc())?
+            }
+            Op::Div => Value::broadcast("div", &input(0)?, &input(1)?, |a, b| a / b)?,
+            Op::Pow => Value::broadcast("pow", &input(0)?, &input(1)?, f64::powf)?,
+            Op::Tanh => input(0)?.map(f64::tanh),
+            Op::Exp => input(0)?.map(f64::exp),
+            Op::Neg => input(0)?.map(|x| -x),
+            Op::Log => input(0)?.map(f64::ln),
+            Op::Log1p => input(0)?.map(f64::ln_1p),
+            Op::Sqrt => input(0)?.map(f64::sqrt),
+            Op::Rsqrt => input(0)?.map(|x| 1.0 / x.sqrt()),
+            Op::Square => input(0)?.map(|x| x * x),
+            Op::Abs => input(0)?.map(f64::abs),
+            Op::Sign => input(0)?.map(|x| if x == 0.0 { 0.0 } else { x.signum() }),
+            Op::Sin => input(0)?.map(f64::sin),
+            Op::Cos => input(0)?.map(f64::cos),
+            Op::Floor => input(0)?.map(f64::floor),
+            Op::Ceil => input(0)?.map(f64::ceil),
+            Op::Round => input(0)?.map(f64::round_ties_even),
+            Op::Reciprocal => input(0)?.map(|x| 1.0 / x),
+            Op::Erf => input(0)?.map(erf),
+            Op::Relu => input(0)?.map(|x| x.max(0.0)),
+            Op::Sigmoid => input(0)?.map(|x| 1.0 / (1.0 + (-x).exp())),
+            Op::Softplus => input(0)?.map(|x| x.max(0.0) + (-x.abs()).exp().ln_1p()),
+            Op::Elu => input(0)?.map(|x| if x > 0.0 { x } else { x.exp_m1() }),
+            Op::Softmax => input(0)?.softmax(false),
+            Op::LogSoftmax => input(0)?.softmax(true),
+            Op::Reduce(reduction, axis) => input(0)?.reduce(axis, |v| reduction.evaluate(v)),
+            Op::Reshape => {
+                let dimensions: Vec<i64> =
+                    node.dims().iter().map(|d| d.size().map_or(-1, |s| s as i64)).collect();
+                input(0)?.reshape(&dimensions)?
+            }
+            Op::Transpose(permutation) => input(0)?.transpose(&permutation),
+            Op::ExpandDims(axis) => input(0)?.expand_dims(axis),
+            Op::Squeeze(axis) => input(0)?.squeeze(axis)?,
+            Op::MatMul => input(0)?.mat_mul(&input(1)?)?,
+        };
+
+        Ok(value)
+    }
+}
+
+fn check_dimensions<const RANK: usize, D: Data<RANK> + 'static>(
+    operation: &'static str,
+    expected: &[Dim],
+    value: &TensorData<RANK, D>,
+) -> Result<(), ShapeError> {
+    let actual: Vec<Dim> = value.dimensions().iter().map(|d| Dim::Fixed(*d as usize)).collect();
+    let compatible = expected.iter().zip(&actual).all(|(e, a)| e.is_compatible_with(a));
+
+    if !compatible {
+        return Err(ShapeError::new(operation, expected.to_vec(), actual));
+    }
+
+    // A tensor without data is all zeros, which always has the right count.
+    let count: u64 = value.dimensions().iter().product();
+
+    match &value.data {
+        Some(values) if values.len() as u64 != count => {
+            Err(ShapeError::new(operation, actual, vec![Dim::Fixed(values.len())]))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Value {
+    pub fn dims(&self) -> &[usize] {
+        &self.dims
+    }
+
+    /// The values in row-major order.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    pub(crate) fn from_tensor_data<const RANK: usize, D: Data<RANK> + 'static>(
+        tensor_data: &TensorData<RANK, D>,
+    ) -> Self {
+        Value {
+            dims: tensor_data.data_type.dimensions().iter().map(|d| *d as usize).collect(),
+            values: tensor_data.values().iter().map(|v| v.to_f64()).collect(),
+        }
+    }
+
+    pub(crate) fn map<F: Fn(f64) -> f64>(&self, f: F) -> Value {
+        Value {
+            dims: self.dims.clone(),
+            values: self.values.iter().map(|v| f(*v)).collect(),
+        }
+    }
+
+    /// The same values with other dimensions, for reshapes and adding or removing axes of size 1.
+    pub(crate) fn with_dims(self, dims: Vec<usize>) -> Value {
+        Value {
+            dims,
+            values: self.values,
+        }
+    }
+
+    /// Resolves the dimensions of a reshape, where -1 stands for the remaining elements.
+    pub(crate) fn reshape(self, dimensions: &[i64]) -> Result<Value, ShapeError> {
+        let count = self.values.len();
+        let known: usize = dimensions.iter().filter(|d| **d >= 0).map(|d| *d as usize).product();
+        let dims: Vec<usize> = dimensions
+            .iter()
+            .map(|d| if *d >= 0 { *d as usize } else { count / known.max(1) })
+            .collect();
+
+        if dims.iter().product::<usize>() != count {
+            let requested = dimensions
+                .iter()
+                .map(|d| if *d >= 0 { Dim::Fixed(*d as usize) } else { Dim::Dynamic })
+                .collect();
+
+            return Err(ShapeError::new("reshape", fixed(&self.dims), requested));
+        }
+
+        Ok(self.with_dims(dims))
+    }
+
+    /// Combines two values elementwise under NumPy broadcasting rules.
+    pub(crate) fn broadcast<F: Fn(f64, f64) -> f64>(
+        operation: &'static str,
+        left: &Value,
+        right: &Value,
+        f: F,
+    ) -> Result<Value, ShapeError> {
+        let rank = left.dims.len().max(right.dims.len());
+        let left_dims = pad(&left.dims, rank);
+        let right_dims = pad(&right.dims, rank);
+        let mut dims = Vec::with_capacity(rank);
+
+        for (l, r) in left_dims.iter().zip(&right_dims) {
+            dims.push(if l == r || *r == 1 {
+                *l
+            } else if *l == 1 {
+                *r
+            } else {
+                return Err(ShapeError::new(operation, fixed(&left.dims), fixed(&right.dims)));
+            });
+        }
+
+        let left_strides = broadcast_strides(&left_dims);
+        let right_strides = broadcast_strides(&right_dims);
+        let count = dims.iter().product();
+        let mut values = Vec::with_capacity(count);
+
+        for index in 0 .. count {
+            let mut remainder = index;
+            let mut left_index = 0;
+            let mut right_index = 0;
+
+            for axis in (0 .. rank).rev() {
+                let i = remainder % dims[axis];
+                remainder /= dims[axis];
+                left_index += i * left_strides[axis];
+                right_index += i * right_strides[axis];
+            }
+
+            values.push(f(left.values[left_index], right.values[right_index]));
+        }
+
+        Ok(Value { dims, values })
+    }
+
+    pub(crate) fn mat_mul(&self, other: &Value) -> Result<Value, ShapeError> {
+        let (m, k) = (self.dims[0], self.dims[1]);
+        let n = other.dims[1];
+
+        if other.dims[0] != k {
+            return Err(ShapeError::new("mat_mul", fixed(&self.dims), fixed(&other.dims)));
+        }
+
+        let mut values = vec![0.0; m * n];
+
+        for i in 0 .. m {
+            for j in 0 .. n {
+                values[i * n + j] = (0 .. k)
+                    .map(|l| self.values[i * k + l] * other.values[l * n + j])
+                    .sum();
+            }
+        }
+
+        Ok(Value {
+            dims: vec![m, n],
+            values,
+        })
+    }
+
+    /// Reduces along `axis`, or along all axes when it is `None`.
+    pub(crate) fn reduce<F: Fn(&[f64]) -> f64>(&self, axis: Option<usize>, f: F) -> Value {
+        let axis = match axis {
+            Some(axis) => axis,
+            None => {
+                return Value {
+                    dims: Vec::new(),
+                    values: vec![f(&self.values)],
+                }
+            }
+        };
+
+        let outer: usize = self.dims[.. axis].iter().product();
+        let size = self.dims[axis];
+        let inner: usize = self.dims[axis + 1 ..].iter().product();
+        let mut values = Vec::with_capacity(outer * inner);
+        let mut group = Vec::with_capacity(size);
+
+        for o in 0 .. outer {
+            for i in 0 .. inner {
+                group.clear();
+                group.extend((0 .. size).map(|j| self.values[(o * size + j) * inner + i]));
+                values.push(f(&group));
+            }
+        }
+
+        let mut dims = self.dims.clone();
+        dims.remove(axis);
+
+        Value { dims, values }
+    }
+
+    pub(crate) fn transpose(&self, permutation: &[usize]) -> Value {
+        let dims: Vec<usize> = permutation.iter().map(|p| self.dims[*p]).collect();
+        let strides = broadcast_strides(&self.dims);
+        let count = self.values.len();
+        let mut values = Vec::with_capacity(count);
+
+        for index in 0 .. count {
+            let mut remainder = index;
+            let mut source = 0;
+
+            for axis in (0 .. dims.len()).rev() {
+                let i = remainder % dims[axis];
+                remainder /= dims[axis];
+                source += i * strides[permutation[axis]];
+            }
+
+            values.push(self.values[source]);
+        }
+
+        Value { dims, values }
+    }
+
+    pub(crate) fn expand_dims(self, axis: usize) -> Value {
+        let mut dims = self.dims.clone();
+        dims.insert(axis, 1);
+        self.with_dims(dims)
+    }
+
+    pub(crate) fn squeeze(self, axis: usize) -> Result<Value, ShapeError> {
+        if self.dims[axis] != 1 {
+            return Err(ShapeError::new("squeeze", fixed(&self.dims), vec![Dim::Fixed(1)]));
+        }
+
+        let mut dims = self.dims.clone();
+        dims.remove(axis);
+        Ok(self.with_dims(dims))
+    }
+
+    /// Softmax over the last axis, or its logarithm.
+    pub(crate) fn softmax(&self, log: bool) -> Value {
+        let size = self.dims.last().copied().unwrap_or(1).max(1);
+        let mut values = Vec::with_capacity(self.values.len());
+
+        for row in self.values.chunks(size) {
+            let max = row.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let sum: f64 = row.iter().map(|v| (v - max).exp()).sum();
+
+            for v in row {
+                values.push(if log { v - max - sum.ln() } else { (v - max).exp() / sum });
+            }
+        }
+
+        Value {
+            dims: self.dims.clone(),
+            values,
+        }
+    }
+}
+
+/// Row-major strides, with stride 0 for axes of size 1 so that they broadcast.
+fn broadcast_strides(dims: &[usize]) -> Vec<usize> {
+    let mut strides = vec![0; dims.len()];
+    let mut stride = 1;
+
+    for axis in (0 .. dims.len()).rev() {
+        strides[axis] = if dims[axis] == 1 { 0 } else { stride };
+        stride *= dims[axis];
+    }
+
+    strides
+}
+
+fn pad(dims: &[usize], rank: usize) -> Vec<usize> {
+    let mut padded = vec![1; rank - dims.len()];
+    padded.extend_from_slice(dims);
+    padded
+}
+
+fn fixed(dims: &[usize]) -> Vec<Dim> {
+    dims.iter().map(|d| Dim::Fixed(*d)).collect()
+}
+
+/// The error function, with the approximation 7.1.26 of Abramowitz and Stegun (absolute error
+/// below 1.5e-7).
+pub(crate) fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let y = 1.0 - polynomial * (-x * x).exp();
+
+    if x >= 0.0 {
+        y
+    } else {
+        -y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{float_feed, float_variable, random_uniform, vector};
+
+    fn matrix(dims: [usize; 2], values: &[f32]) -> TensorData<2, FloatData<2>> {
+        TensorData::new(dims, values)
+    }
+
+    fn value(dims: &[usize], values: &[f64]) -> Value {
+        Value {
+            dims: dims.to_vec(),
+            values: values.to_vec(),
+        }
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-12, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn broadcast_trailing_axes() {
+        let left = value(&[2, 3], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let right = value(&[3], &[10.0, 20.0, 30.0]);
+        let sum = Value::broadcast("add", &left, &right, |a, b| a + b).unwrap();
+
+        assert_eq!(sum.dims, vec![2, 3]);
+        assert_eq!(sum.values, vec![11.0, 22.0, 33.0, 14.0, 25.0, 36.0]);
+    }
+
+    #[test]
+    fn broadcast_outer_product() {
+        let column = value(&[2, 1], &[1.0, 2.0]);
+        let row = value(&[1, 3], &[1.0, 2.0, 3.0]);
+        let product = Value::broadcast("mul", &column, &row, |a, b| a * b).unwrap();
+
+        assert_eq!(product.dims, vec![2, 3]);
+        assert_eq!(product.values, vec![1.0, 2.0, 3.0, 2.0, 4.0, 6.0]);
+    }
+
+    #[test]
+    fn broadcast_incompatible() {
+        let left = value(&[2], &[1.0, 2.0]);
+        let right = value(&[3], &[1.0, 2.0, 3.0]);
+
+        assert!(Value::broadcast("add", &left, &right, |a, b| a + b).is_err());
+    }
+
+    #[test]
+    fn transpose_matrix() {
+        let matrix = value(&[2, 3], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let transposed = matrix.transpose(&[1, 0]);
+
+        assert_eq!(transposed.dims, vec![3, 2]);
+        assert_eq!(transposed.values, vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+    }
+
+    #[test]
+    fn reduce_axes() {
+        let matrix = value(&[2, 3], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let sum = |v: &[f64]| -> f64 { v.iter().sum() };
+
+        let columns = matrix.reduce(Some(0), sum);
+        assert_eq!(columns.dims, vec![3]);
+        assert_eq!(columns.values, vec![5.0, 7.0, 9.0]);
+
+        let rows = matrix.reduce(Some(1), sum);
+        assert_eq!(rows.dims, vec![2]);
+        assert_eq!(rows.values, vec![6.0, 15.0]);
+
+        let all = matrix.reduce(None, sum);
+        assert!(all.dims.is_empty());
+        assert_eq!(all.values, vec![21.0]);
+    }
+
+    #[test]
+    fn softmax_last_axis() {
+        let logits = value(&[2, 2], &[0.0, 3.0_f64.ln(), 1.0, 1.0]);
+
+        assert_close(&logits.softmax(false).values, &[0.25, 0.75, 0.5, 0.5]);
+        assert_close(
+            &logits.softmax(true).values,
+            &[0.25_f64.ln(), 0.75_f64.ln(), 0.5_f64.ln(), 0.5_f64.ln()],
+        );
+    }
+
+    #[test]
+    fn mat_mul_known_values() {
+        let left = value(&[2, 2], &[1.0, 2.0, 3.0, 4.0]);
+        let right = value(&[2, 1], &[5.0, 6.0]);
+        let product = left.mat_mul(&right).unwrap();
+
+        assert_eq!(product.dims, vec![2, 1]);
+        assert_eq!(product.values, vec![17.0, 39.0]);
+    }
+
+    #[test]
+    fn mat_mul_inner_dimensions_mismatch() {
+        let left = value(&[2, 3], &[0.0; 6]);
+        let right = value(&[2, 2], &[0.0; 4]);
+
+        assert!(left.mat_mul(&right).is_err());
+    }
+
+    #[test]
+    fn eval_fed_mat_mul_and_sum() {
+        let x = float_feed("x", [1_usize, 2]);
+        let w: Expr<2, FloatData<2>> = Expr::from(matrix([2, 2], &[1.0, 2.0, 3.0, 4.0]));
+        let mut evaluator = Evaluator::new();
+
+        evaluator.feed(&x, matrix([1, 2], &[1.0, 1.0])).unwrap();
+
+        let product = evaluator.eval(&x.read().mat_mul(w.clone())).unwrap();
+        assert_eq!(product.dimensions(), vec![1, 2]);
+        assert_eq!(product.values(), vec![4.0, 6.0]);
+
+        let sum = evaluator.eval(&x.read().mat_mul(w).reduce_sum_all()).unwrap();
+        assert_eq!(sum.values(), vec![10.0]);
+    }
+
+    #[test]
+    fn eval_variable_initial_and_set_value() {
+        let w = float_variable("w", vector(&[1.0, 2.0]), [2_usize]);
+        let mut evaluator = Evaluator::new();
+
+        assert_eq!(evaluator.eval(&w.read()).unwrap().values(), vec![1.0, 2.0]);
+
+        evaluator.set_variable(&w, &[3.0_f32, 4.0]).unwrap();
+        assert_eq!(evaluator.eval(&w.read()).unwrap().values(), vec![3.0, 4.0]);
+    }
+
+    #[test]
+    fn eval_resolves_dynamic_dimensions_from_feeds() {
+        let x = float_feed("x", [Dim::Dynamic, Dim::Fixed(2)]);
+        let mut evaluator = Evaluator::new();
+
+        evaluator.feed(&x, matrix([3, 2], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0])).unwrap();
+
+        let sums = evaluator.eval(&x.read().reduce_sum::<1, 1>()).unwrap();
+        assert_eq!(sums.data_type().dims(), vec![Dim::Fixed(3)]);
+        assert_eq!(sums.values(), vec![3.0, 7.0, 11.0]);
+    }
+
+    #[test]
+    fn eval_reshape_of_dynamic_input() {
+        let x = float_feed("x", [Dim::Dynamic, Dim::Fixed(2)]);
+        let flat = x.read().reshape([6]);
+        let mut evaluator = Evaluator::new();
+
+        evaluator.feed(&x, matrix([3, 2], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0])).unwrap();
+        assert_eq!(evaluator.eval(&flat).unwrap().dimensions(), vec![6]);
+
+        // The element count of a dynamic input is only known once it is fed.
+        evaluator.feed(&x, matrix([2, 2], &[1.0, 2.0, 3.0, 4.0])).unwrap();
+        assert!(matches!(evaluator.eval(&flat), Err(EvalError::Shape(_))));
+    }
+
+    #[test]
+    fn eval_integer_division_truncates() {
+        let quotient = vector::<Int32Data<1>>(&[7, -7]) / vector::<Int32Data<1>>(&[2, 2]);
+
+        assert_eq!(Evaluator::new().eval(&quotient).unwrap().values(), vec![3, -3]);
+    }
+
+    #[test]
+    fn eval_missing_feed() {
+        let x = float_feed("x", [2_usize]);
+
+        assert!(matches!(
+            Evaluator::new().eval(&x.read()),
+            Err(EvalError::MissingFeed(name)) if name == "x"
+        ));
+    }
+
+    #[test]
+    fn eval_random_initializer_is_unsupported() {
+        let w = float_variable("w", random_uniform([2_usize]), [2_usize]);
+        let mut evaluator = Evaluator::new();
+
+        assert!(matches!(evaluator.eval(&w.read()), Err(EvalError::Unsupported(_))));
+
+        evaluator.set_variable(&w, &[0.5_f32, 0.25]).unwrap();
+        assert_eq!(evaluator.eval(&w.read()).unwrap().values(), vec![0.5, 0.25]);
+    }
+
+    #[test]
+    fn feed_with_wrong_dimensions() {
+        let x = float_feed("x", [2_usize]);
+
+        assert!(matches!(
+            Evaluator::new().feed(&x, &[1.0_f32, 2.0, 3.0]),
+            Err(EvalError::Shape(_))
+        ));
+    }
+}
@@ -1,19 +1,36 @@
-use super::{Expr, ExprImpl, Id};
+use super::{annotate_dimensions, operation_key, Expr, ExprImpl, ExprNode, Id, Op};
 use crate::compiler::{CompiledElement, Compiler};
 use crate::data::*;
+use crate::error::ShapeError;
+use std::fmt;
 use tensorflow::ops;
 use tensorflow::Shape;
 use tensorflow::Status;
 
-pub(crate) struct BinOpExpr<const RANK: usize, D: Data<RANK>> {
+pub(crate) struct BinOpExpr<
+    const RANK: usize,
+    D: Data<RANK>,
+    const LRANK: usize,
+    DL: Data<LRANK>,
+    const RRANK: usize,
+    DR: Data<RRANK>,
+> {
     pub(crate) id: Id,
     pub(crate) op: BinaryOperator,
-    pub(crate) left: Expr<RANK, D>,
-    pub(crate) right: Expr<RANK, D>,
+    pub(crate) left: Expr<LRANK, DL>,
+    pub(crate) right: Expr<RRANK, DR>,
     pub(crate) data_type: D,
 }
 
-impl<const RANK: usize, D: Data<RANK>> ExprImpl<RANK, D> for BinOpExpr<RANK, D> {
+impl<
+        const RANK: usize,
+        D: Data<RANK>,
+        const LRANK: usize,
+        DL: Data<LRANK>,
+        const RRANK: usize,
+        DR: Data<RRANK>,
+    > ExprImpl<RANK, D> for BinOpExpr<RANK, D, LRANK, DL, RRANK, DR>
+{
     fn id(&self) -> Id {
         self.id
     }
@@ -26,8 +43,49 @@ impl<const RANK: usize, D: Data<RANK>> ExprImpl<RANK, D> for BinOpExpr<RANK, D>
         self.data_type.shape()
     }
 
-    fn dimensions(&self) -> Vec<u64> {
-        self.data_type().dimensions()
+    fn dims(&self) -> Vec<Dim> {
+        self.data_type().dims()
+    }
+
+    fn kind(&self) -> &'static str {
+        "BinOpExpr"
+    }
+
+    fn name(&self) -> String {
+        self.op.name().to_string()
+    }
+
+    fn inputs(&self) -> Vec<&dyn ExprNode> {
+        vec![&self.left as &dyn ExprNode, &self.right]
+    }
+
+    fn op(&self) -> Op {
+        self.op.op()
+    }
+
+    fn structural_key(&self) -> Option<String> {
+        Some(operation_key(&self.name(), self.data_type.data_type(), &self.dims()))
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter, annotate: bool) -> fmt::Result {
+        match self.op.symbol() {
+            Some(symbol) => {
+                write!(f, "(")?;
+                self.left.describe(f, annotate)?;
+                write!(f, " {} ", symbol)?;
+                self.right.describe(f, annotate)?;
+                write!(f, ")")?;
+            }
+            None => {
+                write!(f, "{}(", self.op.name())?;
+                self.left.describe(f, annotate)?;
+                write!(f, ", ")?;
+                self.right.describe(f, annotate)?;
+                write!(f, ")")?;
+            }
+        }
+
+        annotate_dimensions(f, &self.dims(), annotate)
     }
 
     fn make_operation(&self, compiler: &mut Compiler) -> Result<CompiledElement, Status> {
@@ -47,6 +105,9 @@ impl<const RANK: usize, D: Data<RANK>> ExprImpl<RANK, D> for BinOpExpr<RANK, D>
             BinaryOperator::Div => {
                 ops::div(left_output, right_output, compiler.borrow_scope_mut())?
             }
+            BinaryOperator::Pow => {
+                ops::pow(left_output, right_output, compiler.borrow_scope_mut())?
+            }
         };
 
         Ok(CompiledElement::Operation(operation))
@@ -58,4 +119,131 @@ pub(crate) enum BinaryOperator {
     Sub,
     Mul,
     Div,
+    Pow,
+}
+
+impl BinaryOperator {
+    pub(crate) fn op(&self) -> Op {
+        match self {
+            BinaryOperator::Add => Op::Add,
+            BinaryOperator::Sub => Op::Sub,
+            BinaryOperator::Mul => Op::Mul,
+            BinaryOperator::Div => Op::Div,
+            BinaryOperator::Pow => Op::Pow,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "add",
+            BinaryOperator::Sub => "sub",
+            BinaryOperator::Mul => "mul",
+            BinaryOperator::Div => "div",
+            BinaryOperator::Pow => "pow",
+        }
+    }
+
+    /// The infix symbol of the operator, if it has one.
+    pub(crate) fn symbol(&self) -> Option<&'static str> {
+        match self {
+            BinaryOperator::Add => Some("+"),
+            BinaryOperator::Sub => Some("-"),
+            BinaryOperator::Mul => Some("*"),
+            BinaryOperator::Div => Some("/"),
+            BinaryOperator::Pow => None,
+        }
+    }
+}
+
+/// Computes the dimensions of `left op right` under NumPy broadcasting rules: shapes are aligned
+/// on their trailing axes and each pair of dimensions must be equal or contain a 1. A dynamic
+/// dimension is assumed to fit, it takes the other dimension unless that is 1.
+pub(crate) fn broadcast_dimensions(
+    op: &BinaryOperator,
+    left: Vec<Dim>,
+    right: Vec<Dim>,
+) -> Result<Vec<Dim>, ShapeError> {
+    let one = Dim::Fixed(1);
+    let rank = left.len().max(right.len());
+    let mut dimensions = vec![one; rank];
+
+    for i in 0 .. rank {
+        let l = if i < left.len() { left[left.len() - 1 - i] } else { one };
+        let r = if i < right.len() { right[right.len() - 1 - i] } else { one };
+
+        dimensions[rank - 1 - i] = if l == r || r == one {
+            l
+        } else if l == one || l.is_dynamic() {
+            r
+        } else if r.is_dynamic() {
+            l
+        } else {
+            return Err(ShapeError::new(op.name(), left, right));
+        };
+    }
+
+    Ok(dimensions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::float_feed;
+
+    fn dims(dimensions: &[Option<usize>]) -> Vec<Dim> {
+        dimensions.iter().map(|d| d.map_or(Dim::Dynamic, Dim::Fixed)).collect()
+    }
+
+    fn broadcast(left: &[Option<usize>], right: &[Option<usize>]) -> Result<Vec<Dim>, ShapeError> {
+        broadcast_dimensions(&BinaryOperator::Add, dims(left), dims(right))
+    }
+
+    #[test]
+    fn broadcast_aligns_trailing_axes() {
+        assert_eq!(broadcast(&[Some(3)], &[Some(2), Some(3)]), Ok(dims(&[Some(2), Some(3)])));
+        assert_eq!(broadcast(&[Some(2), Some(3)], &[Some(3)]), Ok(dims(&[Some(2), Some(3)])));
+        assert_eq!(broadcast(&[], &[Some(2), Some(3)]), Ok(dims(&[Some(2), Some(3)])));
+    }
+
+    #[test]
+    fn broadcast_stretches_ones() {
+        assert_eq!(
+            broadcast(&[Some(2), Some(1)], &[Some(1), Some(4)]),
+            Ok(dims(&[Some(2), Some(4)]))
+        );
+    }
+
+    #[test]
+    fn broadcast_dynamic_dimensions() {
+        // A dynamic dimension takes the other size unless that is 1.
+        assert_eq!(broadcast(&[None, Some(3)], &[Some(3)]), Ok(dims(&[None, Some(3)])));
+        assert_eq!(broadcast(&[None], &[Some(4)]), Ok(dims(&[Some(4)])));
+        assert_eq!(broadcast(&[Some(4)], &[None]), Ok(dims(&[Some(4)])));
+        assert_eq!(broadcast(&[None], &[Some(1)]), Ok(dims(&[None])));
+        assert_eq!(broadcast(&[Some(1)], &[None]), Ok(dims(&[None])));
+        assert_eq!(broadcast(&[None], &[None]), Ok(dims(&[None])));
+    }
+
+    #[test]
+    fn broadcast_incompatible_dimensions() {
+        let error = broadcast(&[Some(2), Some(3)], &[Some(2)]).unwrap_err();
+
+        assert_eq!(error.operation(), "add");
+        assert_eq!(error.left(), dims(&[Some(2), Some(3)]));
+        assert_eq!(error.right(), dims(&[Some(2)]));
+    }
+
+    #[test]
+    fn arithmetic_between_ranks() {
+        let row = float_feed("row", [3_usize]).read();
+        let matrix = float_feed("matrix", [Dim::Dynamic, Dim::Fixed(3)]).read();
+
+        assert_eq!((row.clone() + matrix.clone()).dims(), dims(&[None, Some(3)]));
+        assert_eq!((matrix * row).dims(), dims(&[None, Some(3)]));
+
+        let column = float_feed("column", [2_usize, 1]).read();
+        let other = float_feed("other", [1_usize, 4]).read();
+
+        assert_eq!((column * other).dims(), dims(&[Some(2), Some(4)]));
+    }
 }
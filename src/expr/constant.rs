@@ -1,7 +1,11 @@
-use super::{ExprImpl, Id};
+use super::{describe_leaf, operation_key, ExprImpl, ExprNode, Id, Op};
 use crate::compiler::{CompiledElement, Compiler};
 use crate::data::*;
 use crate::tensordata::TensorData;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::fmt::Write as _;
+use std::hash::Hasher;
 use tensorflow::ops;
 use tensorflow::Shape;
 use tensorflow::Status;
@@ -20,17 +24,72 @@ impl<const RANK: usize, D: Data<RANK> + 'static> ExprImpl<RANK, D> for ConstantE
         self.value.data_type.shape()
     }
 
-    fn dimensions(&self) -> Vec<u64> {
-        self.value.data_type.dimensions()
+    fn dims(&self) -> Vec<Dim> {
+        self.value.data_type.dims()
     }
 
     fn id(&self) -> Id {
         self.id
     }
 
+    fn kind(&self) -> &'static str {
+        "ConstantExpr"
+    }
+
+    fn name(&self) -> String {
+        "constant".to_string()
+    }
+
+    fn inputs(&self) -> Vec<&dyn ExprNode> {
+        Vec::new()
+    }
+
+    fn op(&self) -> Op {
+        Op::Constant(self.value.values().iter().map(|v| v.to_f64()).collect())
+    }
+
+    /// Constants are shared if their element types, dimensions and values are equal. The values
+    /// are hashed, so that large constants don't end up in the key; their `Debug` output
+    /// round-trips, so only a collision of the 64-bit hash can share unequal values.
+    fn structural_key(&self) -> Option<String> {
+        let data_type = self.value.data_type.data_type();
+        let key = operation_key("constant", data_type, &self.dims());
+
+        self.value.data.as_ref().map(|values| {
+            let mut hasher = HashWriter(DefaultHasher::new());
+
+            for value in values {
+                // Writing to a hasher can't fail.
+                let _ = write!(hasher, "{:?},", value);
+            }
+
+            format!("{} {:016x}", key, hasher.0.finish())
+        })
+    }
+
+    /// Scalars are written as their value, other constants as `constant`.
+    fn describe(&self, f: &mut fmt::Formatter, annotate: bool) -> fmt::Result {
+        match &self.value.data {
+            Some(values) if RANK == 0 && values.len() == 1 => {
+                describe_leaf(f, &values[0].to_string(), &self.dims(), annotate)
+            }
+            _ => describe_leaf(f, "constant", &self.dims(), annotate),
+        }
+    }
+
     fn make_operation(&self, compiler: &mut Compiler) -> Result<CompiledElement, Status> {
         let operation = ops::constant(self.value.make_tensor()?, compiler.borrow_scope_mut())?;
 
         Ok(CompiledElement::Operation(operation))
     }
 }
+
+/// Feeds formatted values to a hasher without building a string.
+struct HashWriter(DefaultHasher);
+
+impl fmt::Write for HashWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write(s.as_bytes());
+        Ok(())
+    }
+}
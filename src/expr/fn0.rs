@@ -1,6 +1,7 @@
-use super::{ExprImpl, Id};
+use super::{describe_leaf, ExprImpl, ExprNode, Id, Op};
 use crate::compiler::{CompiledElement, Compiler};
-use crate::data::Data;
+use crate::data::{Data, Dim};
+use std::fmt;
 use tensorflow::ops;
 use tensorflow::Shape;
 use tensorflow::Status;
@@ -24,8 +25,31 @@ impl<const RANK: usize, D: Data<RANK>> ExprImpl<RANK, D> for Fn0Expr<RANK, D> {
         self.data_type.shape()
     }
 
-    fn dimensions(&self) -> Vec<u64> {
-        self.data_type.dimensions()
+    fn dims(&self) -> Vec<Dim> {
+        self.data_type.dims()
+    }
+
+    fn kind(&self) -> &'static str {
+        "Fn0Expr"
+    }
+
+    fn name(&self) -> String {
+        self.function.name().to_string()
+    }
+
+    fn inputs(&self) -> Vec<&dyn ExprNode> {
+        Vec::new()
+    }
+
+    fn op(&self) -> Op {
+        match self.function {
+            TFFunction0::RandomStandardNormal => Op::RandomStandardNormal,
+            TFFunction0::RandomUniform => Op::RandomUniform,
+        }
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter, annotate: bool) -> fmt::Result {
+        describe_leaf(f, &self.name(), &self.dims(), annotate)
     }
 
     fn make_operation(&self, compiler: &mut Compiler) -> Result<CompiledElement, Status> {
@@ -58,3 +82,12 @@ pub(crate) enum TFFunction0 {
     RandomStandardNormal,
     RandomUniform,
 }
+
+impl TFFunction0 {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            TFFunction0::RandomStandardNormal => "random_standard_normal",
+            TFFunction0::RandomUniform => "random_uniform",
+        }
+    }
+}
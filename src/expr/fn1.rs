@@ -1,6 +1,6 @@
-use super::{Expr, ExprImpl, Id};
+use super::{operation_key, Expr, ExprImpl, ExprNode, Id, Op};
 use crate::compiler::{CompiledElement, Compiler};
-use crate::data::Data;
+use crate::data::{Data, Dim};
 use tensorflow::ops;
 use tensorflow::Shape;
 use tensorflow::Status;
@@ -28,16 +28,93 @@ impl<const RANK0: usize, D0: Data<RANK0>, const RANK1: usize, D1: Data<RANK1>> E
         self.data_type.shape()
     }
 
-    fn dimensions(&self) -> Vec<u64> {
-        self.data_type.dimensions()
+    fn dims(&self) -> Vec<Dim> {
+        self.data_type.dims()
+    }
+
+    fn kind(&self) -> &'static str {
+        "Fn1Expr"
+    }
+
+    fn name(&self) -> String {
+        self.function.name()
+    }
+
+    fn inputs(&self) -> Vec<&dyn ExprNode> {
+        vec![&self.arg as &dyn ExprNode]
+    }
+
+    fn op(&self) -> Op {
+        self.function.op()
+    }
+
+    fn structural_key(&self) -> Option<String> {
+        Some(operation_key(&self.name(), self.data_type.data_type(), &self.dims()))
     }
 
     fn make_operation(&self, compiler: &mut Compiler) -> Result<CompiledElement, Status> {
         let arg_output = compiler.get_output(&self.arg)?;
 
-        let operation = match self.function {
+        let operation = match &self.function {
             TFFunction1::Tanh => ops::tanh(arg_output, compiler.borrow_scope_mut())?,
             TFFunction1::Exp => ops::exp(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Neg => ops::neg(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Log => ops::log(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Log1p => ops::log1p(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Sqrt => ops::sqrt(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Rsqrt => ops::rsqrt(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Square => ops::square(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Abs => ops::abs(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Sign => ops::sign(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Sin => ops::sin(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Cos => ops::cos(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Floor => ops::floor(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Ceil => ops::ceil(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Round => ops::round(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Reciprocal => ops::reciprocal(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Erf => ops::erf(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Relu => ops::relu(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Sigmoid => ops::sigmoid(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Softplus => ops::softplus(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Elu => ops::elu(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Softmax => ops::softmax(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::LogSoftmax => ops::log_softmax(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Reduce(reduction, axis) => {
+                let axes: Vec<i32> = match axis {
+                    Some(axis) => vec![*axis as i32],
+                    None => (0 .. RANK1 as i32).collect(),
+                };
+                let axes = ops::constant(&axes[..], compiler.borrow_scope_mut())?;
+                let scope = compiler.borrow_scope_mut();
+
+                match reduction {
+                    Reduction::Sum => ops::sum(arg_output, axes, scope)?,
+                    Reduction::Mean => ops::mean(arg_output, axes, scope)?,
+                    Reduction::Max => ops::max(arg_output, axes, scope)?,
+                    Reduction::Min => ops::min(arg_output, axes, scope)?,
+                    Reduction::Prod => ops::prod(arg_output, axes, scope)?,
+                }
+            }
+            TFFunction1::Reshape => {
+                let shape = ops::constant(
+                    &self.data_type.dimensions_i64()[..],
+                    compiler.borrow_scope_mut(),
+                )?;
+                ops::reshape(arg_output, shape, compiler.borrow_scope_mut())?
+            }
+            TFFunction1::Transpose(permutation) => {
+                let permutation: Vec<i32> = permutation.iter().map(|p| *p as i32).collect();
+                let permutation = ops::constant(&permutation[..], compiler.borrow_scope_mut())?;
+                ops::transpose(arg_output, permutation, compiler.borrow_scope_mut())?
+            }
+            TFFunction1::ExpandDims(axis) => {
+                let axis = ops::constant(&[*axis as i32][..], compiler.borrow_scope_mut())?;
+                ops::expand_dims(arg_output, axis, compiler.borrow_scope_mut())?
+            }
+            TFFunction1::Squeeze(axis) => ops::Squeeze::new()
+                .squeeze_dims(vec![*axis as i64])
+                .build(arg_output, compiler.borrow_scope_mut())?,
+            TFFunction1::Identity => ops::identity(arg_output, compiler.borrow_scope_mut())?,
         };
 
         Ok(CompiledElement::Operation(operation))
@@ -47,4 +124,140 @@ impl<const RANK0: usize, D0: Data<RANK0>, const RANK1: usize, D1: Data<RANK1>> E
 pub(crate) enum TFFunction1 {
     Tanh,
     Exp,
+    Neg,
+    Log,
+    Log1p,
+    Sqrt,
+    Rsqrt,
+    Square,
+    Abs,
+    Sign,
+    Sin,
+    Cos,
+    Floor,
+    Ceil,
+    Round,
+    Reciprocal,
+    Erf,
+    Relu,
+    Sigmoid,
+    Softplus,
+    Elu,
+    /// Softmax over the last axis.
+    Softmax,
+    /// Log-softmax over the last axis.
+    LogSoftmax,
+    /// Reduce along a single axis, or along all axes when the axis is `None`.
+    Reduce(Reduction, Option<usize>),
+    /// Reshape to the dimensions of the output data type.
+    Reshape,
+    Transpose(Vec<usize>),
+    ExpandDims(usize),
+    Squeeze(usize),
+    /// The same values under a different data type, e.g. static to dynamic dimensions.
+    Identity,
+}
+
+impl TFFunction1 {
+    pub(crate) fn op(&self) -> Op {
+        match self {
+            TFFunction1::Tanh => Op::Tanh,
+            TFFunction1::Exp => Op::Exp,
+            TFFunction1::Neg => Op::Neg,
+            TFFunction1::Log => Op::Log,
+            TFFunction1::Log1p => Op::Log1p,
+            TFFunction1::Sqrt => Op::Sqrt,
+            TFFunction1::Rsqrt => Op::Rsqrt,
+            TFFunction1::Square => Op::Square,
+            TFFunction1::Abs => Op::Abs,
+            TFFunction1::Sign => Op::Sign,
+            TFFunction1::Sin => Op::Sin,
+            TFFunction1::Cos => Op::Cos,
+            TFFunction1::Floor => Op::Floor,
+            TFFunction1::Ceil => Op::Ceil,
+            TFFunction1::Round => Op::Round,
+            TFFunction1::Reciprocal => Op::Reciprocal,
+            TFFunction1::Erf => Op::Erf,
+            TFFunction1::Relu => Op::Relu,
+            TFFunction1::Sigmoid => Op::Sigmoid,
+            TFFunction1::Softplus => Op::Softplus,
+            TFFunction1::Elu => Op::Elu,
+            TFFunction1::Softmax => Op::Softmax,
+            TFFunction1::LogSoftmax => Op::LogSoftmax,
+            TFFunction1::Reduce(reduction, axis) => Op::Reduce(*reduction, *axis),
+            TFFunction1::Reshape => Op::Reshape,
+            TFFunction1::Transpose(permutation) => Op::Transpose(permutation.clone()),
+            TFFunction1::ExpandDims(axis) => Op::ExpandDims(*axis),
+            TFFunction1::Squeeze(axis) => Op::Squeeze(*axis),
+            TFFunction1::Identity => Op::Identity,
+        }
+    }
+
+    pub(crate) fn name(&self) -> String {
+        match self {
+            TFFunction1::Tanh => "tanh".to_string(),
+            TFFunction1::Exp => "exp".to_string(),
+            TFFunction1::Neg => "neg".to_string(),
+            TFFunction1::Log => "log".to_string(),
+            TFFunction1::Log1p => "log1p".to_string(),
+            TFFunction1::Sqrt => "sqrt".to_string(),
+            TFFunction1::Rsqrt => "rsqrt".to_string(),
+            TFFunction1::Square => "square".to_string(),
+            TFFunction1::Abs => "abs".to_string(),
+            TFFunction1::Sign => "sign".to_string(),
+            TFFunction1::Sin => "sin".to_string(),
+            TFFunction1::Cos => "cos".to_string(),
+            TFFunction1::Floor => "floor".to_string(),
+            TFFunction1::Ceil => "ceil".to_string(),
+            TFFunction1::Round => "round".to_string(),
+            TFFunction1::Reciprocal => "reciprocal".to_string(),
+            TFFunction1::Erf => "erf".to_string(),
+            TFFunction1::Relu => "relu".to_string(),
+            TFFunction1::Sigmoid => "sigmoid".to_string(),
+            TFFunction1::Softplus => "softplus".to_string(),
+            TFFunction1::Elu => "elu".to_string(),
+            TFFunction1::Softmax => "softmax".to_string(),
+            TFFunction1::LogSoftmax => "log_softmax".to_string(),
+            TFFunction1::Reduce(reduction, Some(axis)) => {
+                format!("reduce_{}<{}>", reduction.name(), axis)
+            }
+            TFFunction1::Reduce(reduction, None) => format!("reduce_{}_all", reduction.name()),
+            TFFunction1::Reshape => "reshape".to_string(),
+            TFFunction1::Transpose(permutation) => format!("transpose{:?}", permutation),
+            TFFunction1::ExpandDims(axis) => format!("expand_dims<{}>", axis),
+            TFFunction1::Squeeze(axis) => format!("squeeze<{}>", axis),
+            TFFunction1::Identity => "identity".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reduction {
+    Sum,
+    Mean,
+    Max,
+    Min,
+    Prod,
+}
+
+impl Reduction {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Reduction::Sum => "sum",
+            Reduction::Mean => "mean",
+            Reduction::Max => "max",
+            Reduction::Min => "min",
+            Reduction::Prod => "prod",
+        }
+    }
+
+    pub(crate) fn evaluate(&self, values: &[f64]) -> f64 {
+        match self {
+            Reduction::Sum => values.iter().sum(),
+            Reduction::Mean => values.iter().sum::<f64>() / values.len() as f64,
+            Reduction::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Reduction::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            Reduction::Prod => values.iter().product(),
+        }
+    }
 }
@@ -1,6 +1,6 @@
-use super::{Expr, ExprImpl, Id};
+use super::{operation_key, Expr, ExprImpl, ExprNode, Id, Op};
 use crate::compiler::{CompiledElement, Compiler};
-use crate::data::Data;
+use crate::data::{Data, Dim};
 use tensorflow::ops;
 use tensorflow::Shape;
 use tensorflow::Status;
@@ -29,8 +29,30 @@ impl<const RANK0: usize, D0: Data<RANK0>, const RANK1: usize, D1: Data<RANK1>, c
         self.data_type.shape()
     }
 
-    fn dimensions(&self) -> Vec<u64> {
-        self.data_type.dimensions()
+    fn dims(&self) -> Vec<Dim> {
+        self.data_type.dims()
+    }
+
+    fn kind(&self) -> &'static str {
+        "Fn2Expr"
+    }
+
+    fn name(&self) -> String {
+        self.function.name().to_string()
+    }
+
+    fn inputs(&self) -> Vec<&dyn ExprNode> {
+        vec![&self.arg1 as &dyn ExprNode, &self.arg2]
+    }
+
+    fn op(&self) -> Op {
+        match self.function {
+            TFFunction2::MatMul => Op::MatMul,
+        }
+    }
+
+    fn structural_key(&self) -> Option<String> {
+        Some(operation_key(&self.name(), self.data_type.data_type(), &self.dims()))
     }
 
     fn make_operation(&self, compiler: &mut Compiler) -> Result<CompiledElement, Status> {
@@ -48,3 +70,11 @@ impl<const RANK0: usize, D0: Data<RANK0>, const RANK1: usize, D1: Data<RANK1>, c
 pub(crate) enum TFFunction2 {
     MatMul,
 }
+
+impl TFFunction2 {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            TFFunction2::MatMul => "mat_mul",
+        }
+    }
+}
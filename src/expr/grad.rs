@@ -0,0 +1,225 @@
+use super::{get_id, operation_key, Expr, ExprImpl, ExprNode, Id, Op, Variable};
+use crate::compiler::{CompiledElement, Compiler};
+use crate::data::{Data, Dim, FloatingData, NoData};
+use std::rc::Rc;
+use tensorflow::ops;
+use tensorflow::Code;
+use tensorflow::Shape;
+use tensorflow::Status;
+
+/// The gradients of `y` with respect to all of `xs`, built with a single call to TensorFlow's
+/// symbolic gradients (the same machinery `Optimizer::minimize` uses). A non-scalar `y` is summed
+/// first. The gradients are read with `GradientExpr`, one per `x`.
+pub(crate) struct GradientsExpr<const YRANK: usize, DY: Data<YRANK>> {
+    pub(crate) id: Id,
+    pub(crate) y: Expr<YRANK, DY>,
+    pub(crate) xs: Vec<Rc<dyn ExprNode>>,
+}
+
+impl<const YRANK: usize, DY: Data<YRANK>> ExprImpl<0, NoData> for GradientsExpr<YRANK, DY> {
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    fn data_type(&self) -> NoData {
+        NoData::new()
+    }
+
+    fn shape(&self) -> Shape {
+        self.data_type().shape()
+    }
+
+    fn dims(&self) -> Vec<Dim> {
+        self.data_type().dims()
+    }
+
+    fn kind(&self) -> &'static str {
+        "GradientsExpr"
+    }
+
+    fn name(&self) -> String {
+        "gradients".to_string()
+    }
+
+    fn inputs(&self) -> Vec<&dyn ExprNode> {
+        let mut inputs = vec![&self.y as &dyn ExprNode];
+        inputs.extend(self.xs.iter().map(|x| x.as_ref()));
+        inputs
+    }
+
+    fn op(&self) -> Op {
+        Op::Gradients
+    }
+
+    fn structural_key(&self) -> Option<String> {
+        Some(self.name())
+    }
+
+    fn make_operation(&self, compiler: &mut Compiler) -> Result<CompiledElement, Status> {
+        let y_output = compiler.get_output(&self.y)?;
+        let x_outputs = self
+            .xs
+            .iter()
+            .map(|x| compiler.lower(x.as_ref())?.output())
+            .collect::<Result<Vec<_>, Status>>()?;
+
+        let gradients = compiler
+            .backend
+            .scope
+            .graph_mut()
+            .add_gradients(None, &[y_output], &x_outputs, None)?;
+
+        Ok(CompiledElement::Gradients(gradients))
+    }
+}
+
+/// The gradient at `index` of a `GradientsExpr`, with the type and shape of `x`. If `y` does not
+/// depend on `x` the gradient is zero.
+pub(crate) struct GradientExpr<const RANK: usize, D: Data<RANK>> {
+    pub(crate) id: Id,
+    pub(crate) gradients: Expr<0, NoData>,
+    pub(crate) index: usize,
+    pub(crate) x: Expr<RANK, D>,
+}
+
+impl<const RANK: usize, D: Data<RANK>> ExprImpl<RANK, D> for GradientExpr<RANK, D> {
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    fn data_type(&self) -> D {
+        self.x.0.data_type()
+    }
+
+    fn shape(&self) -> Shape {
+        self.x.0.shape()
+    }
+
+    fn dims(&self) -> Vec<Dim> {
+        self.x.0.dims()
+    }
+
+    fn kind(&self) -> &'static str {
+        "GradientExpr"
+    }
+
+    fn name(&self) -> String {
+        format!("gradient<{}>", self.index)
+    }
+
+    fn inputs(&self) -> Vec<&dyn ExprNode> {
+        vec![&self.gradients as &dyn ExprNode]
+    }
+
+    fn op(&self) -> Op {
+        Op::Gradient(self.index)
+    }
+
+    fn structural_key(&self) -> Option<String> {
+        Some(operation_key(&self.name(), self.x.0.data_type().data_type(), &self.dims()))
+    }
+
+    fn make_operation(&self, compiler: &mut Compiler) -> Result<CompiledElement, Status> {
+        let gradient = match compiler.lower(&self.gradients)? {
+            CompiledElement::Gradients(gradients) => gradients.get(self.index).cloned().flatten(),
+            _ => {
+                return Err(Status::new_set_lossy(
+                    Code::Internal,
+                    "The input of a gradient is not a gradients node",
+                ))
+            }
+        };
+
+        // The gradient outputs live under a generated prefix, the identity gives the expression
+        // an operation of its own.
+        let operation = match gradient {
+            Some(gradient) => ops::identity(gradient, compiler.borrow_scope_mut())?,
+            None => {
+                let x_output = compiler.get_output(&self.x)?;
+                ops::zeros_like(x_output, compiler.borrow_scope_mut())?
+            }
+        };
+
+        Ok(CompiledElement::Operation(operation))
+    }
+}
+
+pub(crate) fn gradients<const YRANK: usize, DY: Data<YRANK> + 'static>(
+    y: &Expr<YRANK, DY>,
+    xs: Vec<Rc<dyn ExprNode>>,
+) -> Expr<0, NoData> {
+    Expr(Rc::new(GradientsExpr {
+        id: get_id(),
+        y: y.clone(),
+        xs,
+    }))
+}
+
+pub(crate) fn gradient<const RANK: usize, D: Data<RANK> + 'static>(
+    gradients: &Expr<0, NoData>,
+    index: usize,
+    x: Expr<RANK, D>,
+) -> Expr<RANK, D> {
+    Expr(Rc::new(GradientExpr {
+        id: get_id(),
+        gradients: gradients.clone(),
+        index,
+        x,
+    }))
+}
+
+/// What `Expr::gradients_of` takes gradients with respect to: a variable, or a tuple of variables
+/// of any ranks and data types.
+pub trait GradientTargets {
+    type Gradients;
+
+    /// The expressions to differentiate with respect to, in order.
+    fn targets(&self) -> Vec<Rc<dyn ExprNode>>;
+
+    /// The gradients of the targets, read from `gradients` starting at `index`.
+    fn select(&self, gradients: &Expr<0, NoData>, index: &mut usize) -> Self::Gradients;
+}
+
+impl<const RANK: usize, D: FloatingData<RANK> + 'static> GradientTargets for &Variable<RANK, D> {
+    type Gradients = Expr<RANK, D>;
+
+    fn targets(&self) -> Vec<Rc<dyn ExprNode>> {
+        vec![Rc::new(self.read())]
+    }
+
+    fn select(&self, gradients: &Expr<0, NoData>, index: &mut usize) -> Self::Gradients {
+        let gradient = gradient(gradients, *index, self.read());
+        *index += 1;
+        gradient
+    }
+}
+
+macro_rules! gradient_targets_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: GradientTargets),*> GradientTargets for ($($name,)*) {
+            type Gradients = ($($name::Gradients,)*);
+
+            #[allow(non_snake_case)]
+            fn targets(&self) -> Vec<Rc<dyn ExprNode>> {
+                let ($($name,)*) = self;
+                let mut targets = Vec::new();
+                $(targets.extend($name.targets());)*
+                targets
+            }
+
+            #[allow(non_snake_case)]
+            fn select(&self, gradients: &Expr<0, NoData>, index: &mut usize) -> Self::Gradients {
+                let ($($name,)*) = self;
+
+                ($($name.select(gradients, index),)*)
+            }
+        }
+    };
+}
+
+gradient_targets_tuple!(A);
+gradient_targets_tuple!(A, B);
+gradient_targets_tuple!(A, B, C);
+gradient_targets_tuple!(A, B, C, E);
+gradient_targets_tuple!(A, B, C, E, F);
+gradient_targets_tuple!(A, B, C, E, F, G);
@@ -1,11 +1,15 @@
 use crate::compiler::CompiledElement;
 use crate::compiler::Compiler;
 use crate::data::*;
+use crate::error::ShapeError;
 use crate::tensordata::TensorData;
-use std::ops::{Add, Div, Mul, Sub};
+use std::convert::identity;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
 use std::rc::Rc;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
+use tensorflow::DataType;
 use tensorflow::Shape;
 use tensorflow::Status;
 
@@ -14,14 +18,32 @@ mod constant;
 mod fn0;
 mod fn1;
 mod fn2;
+mod grad;
+mod op;
 mod optimize;
 mod placeholder;
+mod static_shape;
 mod variable;
-
+mod visit;
+
+pub use fn1::Reduction;
+pub use grad::GradientTargets;
+pub use op::Op;
+pub use optimize::AdaDelta;
+pub use optimize::Adam;
+pub use optimize::GradientDescent;
+pub use optimize::GradientTransform;
+pub use optimize::Momentum;
+pub use optimize::OptimizerConfig;
+pub use optimize::RmsProp;
+pub use optimize::TransformedOptimizer;
 pub use placeholder::Placeholder;
 pub use placeholder::PlaceholderRef;
 pub use variable::Variable;
 pub use variable::VariableRef;
+pub use visit::walk;
+pub use visit::ExprNode;
+pub use visit::ExprVisitor;
 
 static COUNTER: AtomicUsize = AtomicUsize::new(1);
 
@@ -34,135 +56,694 @@ pub(crate) fn get_id() -> Id {
 pub(crate) trait ExprImpl<const RANK: usize, D: Data<RANK>> {
     fn data_type(&self) -> D;
     fn shape(&self) -> Shape;
-    fn dimensions(&self) -> Vec<u64>;
+    fn dims(&self) -> Vec<Dim>;
     fn id(&self) -> Id;
+    fn kind(&self) -> &'static str;
+    fn name(&self) -> String;
+    fn inputs(&self) -> Vec<&dyn ExprNode>;
+    fn op(&self) -> Op;
     fn make_operation(&self, compiler: &mut Compiler) -> Result<CompiledElement, Status>;
+
+    /// Identifies the operation of a pure expression apart from its inputs. Expressions with the
+    /// same key on the same inputs compute the same value, so they can be emitted once, see
+    /// `Compiler::with_common_subexpression_elimination`. `None` for placeholders, variables,
+    /// random numbers and anything else that must not be shared.
+    fn structural_key(&self) -> Option<String> {
+        None
+    }
+
+    /// Writes the expression in infix notation, annotating every node with its dimensions if
+    /// `annotate` is set. Defaults to a function call of `name` on the inputs.
+    fn describe(&self, f: &mut fmt::Formatter, annotate: bool) -> fmt::Result {
+        write!(f, "{}(", self.name())?;
+
+        for (i, input) in self.inputs().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            input.describe(f, annotate)?;
+        }
+
+        write!(f, ")")?;
+        annotate_dimensions(f, &self.dims(), annotate)
+    }
+}
+
+/// The structural key of a pure operation, e.g. `add Float [2, ?]`.
+pub(crate) fn operation_key(name: &str, data_type: DataType, dimensions: &[Dim]) -> String {
+    format!("{} {:?} {:?}", name, data_type, dimensions)
+}
+
+/// Writes a leaf of an expression tree, e.g. `input: [2, 1]`.
+pub(crate) fn describe_leaf(
+    f: &mut fmt::Formatter,
+    name: &str,
+    dimensions: &[Dim],
+    annotate: bool,
+) -> fmt::Result {
+    write!(f, "{}", name)?;
+    annotate_dimensions(f, dimensions, annotate)
+}
+
+pub(crate) fn annotate_dimensions(
+    f: &mut fmt::Formatter,
+    dimensions: &[Dim],
+    annotate: bool,
+) -> fmt::Result {
+    if annotate {
+        write!(f, ": {:?}", dimensions)?;
+    }
+
+    Ok(())
 }
 
 #[derive(Clone)]
 pub struct Expr<const RANK: usize, D: Data<RANK>>(pub(crate) Rc<dyn ExprImpl<RANK, D>>);
 
-impl<const RANK: usize, D: Data<RANK> + 'static> Add<Expr<RANK, D>> for Expr<RANK, D> {
-    type Output = Expr<RANK, D>;
+impl<const RANK: usize, D: Data<RANK>> Expr<RANK, D> {
+    /// Renders the expression as a Graphviz DOT graph, see `dot::to_dot`.
+    pub fn to_dot(&self) -> String {
+        crate::dot::to_dot(self)
+    }
+}
 
-    fn add(self, rhs: Expr<RANK, D>) -> Expr<RANK, D> {
-        let data_type = self.0.data_type();
+/// Infix notation, e.g. `tanh(mat_mul(weight_layer_2, tanh(mat_mul(weight_layer_1, input))))`.
+impl<const RANK: usize, D: Data<RANK>> fmt::Display for Expr<RANK, D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.describe(f, false)
+    }
+}
 
-        Expr(Rc::new(binop::BinOpExpr {
-            id: get_id(),
-            op: binop::BinaryOperator::Add,
-            left: self.clone(),
-            right: rhs.clone(),
-            data_type,
-        }))
+/// Infix notation with the dimensions of every node, e.g.
+/// `mat_mul(weight: [1, 2], input: [2, 1]): [1, 1]`.
+impl<const RANK: usize, D: Data<RANK>> fmt::Debug for Expr<RANK, D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.describe(f, true)
     }
 }
 
-impl<const RANK: usize, D: Data<RANK> + 'static> Sub<Expr<RANK, D>> for Expr<RANK, D> {
-    type Output = Expr<RANK, D>;
+/// Arithmetic with NumPy-style broadcasting that reports incompatible shapes instead of
+/// panicking. The operators `+`, `-`, `*` and `/` panic with the same error.
+pub trait TryArithmetic<Rhs> {
+    type Output;
 
-    fn sub(self, rhs: Expr<RANK, D>) -> Expr<RANK, D> {
-        let data_type = self.0.data_type();
+    fn try_add(self, rhs: Rhs) -> Result<Self::Output, ShapeError>;
+    fn try_sub(self, rhs: Rhs) -> Result<Self::Output, ShapeError>;
+    fn try_mul(self, rhs: Rhs) -> Result<Self::Output, ShapeError>;
+    fn try_div(self, rhs: Rhs) -> Result<Self::Output, ShapeError>;
+}
 
-        Expr(Rc::new(binop::BinOpExpr {
-            id: get_id(),
-            op: binop::BinaryOperator::Sub,
-            left: self.clone(),
-            right: rhs.clone(),
-            data_type,
-        }))
+fn broadcast<
+    const LRANK: usize,
+    DL: Data<LRANK> + 'static,
+    const RRANK: usize,
+    DR: Data<RRANK> + 'static,
+    const RANK: usize,
+    D: RankedData<RANK>,
+>(
+    op: binop::BinaryOperator,
+    left: Expr<LRANK, DL>,
+    right: Expr<RRANK, DR>,
+) -> Result<Expr<RANK, D>, ShapeError> {
+    let dimensions = binop::broadcast_dimensions(&op, left.0.dims(), right.0.dims())?;
+    let mut shape = [Dim::Fixed(1); RANK];
+
+    for (slot, dimension) in shape.iter_mut().zip(dimensions) {
+        *slot = dimension;
     }
+
+    Ok(Expr(Rc::new(binop::BinOpExpr {
+        id: get_id(),
+        op,
+        left,
+        right,
+        data_type: D::from(shape),
+    })))
 }
 
-impl<const RANK: usize, D: Data<RANK> + 'static> Mul<Expr<RANK, D>> for Expr<RANK, D> {
-    type Output = Expr<RANK, D>;
+/// Wraps a Rust literal as a scalar constant so it can take part in broadcasting arithmetic.
+fn literal<const RANK: usize, D: RankedData<RANK>>(value: D::Element) -> Expr<0, D::WithRank<0>> {
+    Expr(Rc::new(constant::ConstantExpr {
+        id: get_id(),
+        value: TensorData::new(D::with_dimensions([]), &[value]),
+    }))
+}
 
-    fn mul(self, rhs: Expr<RANK, D>) -> Expr<RANK, D> {
-        let data_type = self.0.data_type();
+macro_rules! arithmetic {
+    ([$($generics:tt)*], $lhs:ty, $rhs:ty, $output:ty) => {
+        arithmetic!([$($generics)*], $lhs, $rhs, $output, identity, identity);
+    };
+    ([$($generics:tt)*], $lhs:ty, $rhs:ty, $output:ty, $left:expr, $right:expr) => {
+        impl<$($generics)*> TryArithmetic<$rhs> for $lhs {
+            type Output = $output;
+
+            fn try_add(self, rhs: $rhs) -> Result<$output, ShapeError> {
+                broadcast(binop::BinaryOperator::Add, $left(self), $right(rhs))
+            }
+
+            fn try_sub(self, rhs: $rhs) -> Result<$output, ShapeError> {
+                broadcast(binop::BinaryOperator::Sub, $left(self), $right(rhs))
+            }
+
+            fn try_mul(self, rhs: $rhs) -> Result<$output, ShapeError> {
+                broadcast(binop::BinaryOperator::Mul, $left(self), $right(rhs))
+            }
+
+            fn try_div(self, rhs: $rhs) -> Result<$output, ShapeError> {
+                broadcast(binop::BinaryOperator::Div, $left(self), $right(rhs))
+            }
+        }
+
+        impl<$($generics)*> Add<$rhs> for $lhs {
+            type Output = $output;
+
+            fn add(self, rhs: $rhs) -> $output {
+                self.try_add(rhs).unwrap_or_else(|e| panic!("{}", e))
+            }
+        }
+
+        impl<$($generics)*> Sub<$rhs> for $lhs {
+            type Output = $output;
+
+            fn sub(self, rhs: $rhs) -> $output {
+                self.try_sub(rhs).unwrap_or_else(|e| panic!("{}", e))
+            }
+        }
+
+        impl<$($generics)*> Mul<$rhs> for $lhs {
+            type Output = $output;
+
+            fn mul(self, rhs: $rhs) -> $output {
+                self.try_mul(rhs).unwrap_or_else(|e| panic!("{}", e))
+            }
+        }
+
+        impl<$($generics)*> Div<$rhs> for $lhs {
+            type Output = $output;
+
+            fn div(self, rhs: $rhs) -> $output {
+                self.try_div(rhs).unwrap_or_else(|e| panic!("{}", e))
+            }
+        }
+    };
+}
+
+arithmetic!(
+    [const RANK: usize, D: NumericData<RANK> + RankedData<RANK>],
+    Expr<RANK, D>,
+    Expr<RANK, D>,
+    Expr<RANK, D>
+);
+
+/// Arithmetic between a lower rank and a higher rank expression, in both operand orders. The
+/// result has the rank and data type of the higher rank operand.
+macro_rules! broadcast_arithmetic {
+    ($(($low:literal, $high:literal)),*) => {
+        $(
+            arithmetic!(
+                [
+                    DL: NumericData<$low> + RankedData<$low>,
+                    DH: NumericData<$high> + RankedData<$high, Element = DL::Element>
+                ],
+                Expr<$low, DL>,
+                Expr<$high, DH>,
+                Expr<$high, DH>
+            );
+
+            arithmetic!(
+                [
+                    DL: NumericData<$low> + RankedData<$low>,
+                    DH: NumericData<$high> + RankedData<$high, Element = DL::Element>
+                ],
+                Expr<$high, DH>,
+                Expr<$low, DL>,
+                Expr<$high, DH>
+            );
+        )*
+    };
+}
+
+broadcast_arithmetic!(
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4)
+);
+
+/// Arithmetic between an expression and a Rust literal of the same element type, in both
+/// operand orders, e.g. `expr * 2.0_f32` or `1.0 - expr`.
+macro_rules! literal_arithmetic {
+    ($($element:ty),*) => {
+        $(
+            arithmetic!(
+                [const RANK: usize, D: NumericData<RANK> + RankedData<RANK, Element = $element>],
+                Expr<RANK, D>,
+                $element,
+                Expr<RANK, D>,
+                identity,
+                literal::<RANK, D>
+            );
+
+            arithmetic!(
+                [const RANK: usize, D: NumericData<RANK> + RankedData<RANK, Element = $element>],
+                $element,
+                Expr<RANK, D>,
+                Expr<RANK, D>,
+                literal::<RANK, D>,
+                identity
+            );
+        )*
+    };
+}
+
+literal_arithmetic!(f32, f64, i32, i64, u8);
+
+impl<const R: usize, D: FloatingData<R> + 'static> Expr<R, D> {
+    pub fn tanh(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Tanh)
+    }
+
+    pub fn exp(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Exp)
+    }
+
+    pub fn log(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Log)
+    }
+
+    pub fn log1p(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Log1p)
+    }
 
-        Expr(Rc::new(binop::BinOpExpr {
+    pub fn sqrt(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Sqrt)
+    }
+
+    pub fn rsqrt(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Rsqrt)
+    }
+
+    pub fn square(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Square)
+    }
+
+    pub fn abs(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Abs)
+    }
+
+    pub fn sign(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Sign)
+    }
+
+    pub fn sin(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Sin)
+    }
+
+    pub fn cos(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Cos)
+    }
+
+    pub fn floor(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Floor)
+    }
+
+    pub fn ceil(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Ceil)
+    }
+
+    pub fn round(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Round)
+    }
+
+    pub fn reciprocal(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Reciprocal)
+    }
+
+    pub fn erf(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Erf)
+    }
+
+    pub fn relu(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Relu)
+    }
+
+    pub fn sigmoid(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Sigmoid)
+    }
+
+    pub fn softplus(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Softplus)
+    }
+
+    pub fn elu(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Elu)
+    }
+
+    /// Softmax over the last axis.
+    pub fn softmax(self) -> Expr<R, D> {
+        const {
+            assert!(R >= 1, "softmax needs at least one axis");
+        }
+
+        self.unary(fn1::TFFunction1::Softmax)
+    }
+
+    /// Log-softmax over the last axis.
+    pub fn log_softmax(self) -> Expr<R, D> {
+        const {
+            assert!(R >= 1, "log_softmax needs at least one axis");
+        }
+
+        self.unary(fn1::TFFunction1::LogSoftmax)
+    }
+
+    /// Minimizes `self` with respect to `vars` using the given optimizer, e.g.
+    /// `loss.minimize_with(Adam::new().learning_rate(0.001_f32), &vars)`.
+    pub fn minimize_with<O: OptimizerConfig<Element = D::Element> + 'static>(
+        self,
+        optimizer: O,
+        vars: &[VariableRef],
+    ) -> Expr<0, NoData> {
+        Expr(Rc::new(optimize::MinimizeExpr {
             id: get_id(),
-            op: binop::BinaryOperator::Mul,
-            left: self.clone(),
-            right: rhs.clone(),
-            data_type,
+            loss: self,
+            variables: vars.to_vec(),
+            optimizer,
         }))
     }
-}
 
-impl<const RANK: usize, D: Data<RANK> + 'static> Div<Expr<RANK, D>> for Expr<RANK, D> {
-    type Output = Expr<RANK, D>;
+    /// The gradient of `self` with respect to `x`, see `grad`.
+    pub fn grad<const XR: usize, DX: FloatingData<XR> + 'static>(
+        &self,
+        x: &Expr<XR, DX>,
+    ) -> Expr<XR, DX> {
+        grad(self, x)
+    }
+
+    /// The gradients of `self` with respect to `variables`, in the same order, e.g. for custom
+    /// update rules or gradient norms. All of them come from one gradient computation. The
+    /// variables must share a rank and data type, see `gradients_of` for mixed variables.
+    pub fn gradients<const VR: usize, DV: FloatingData<VR> + 'static>(
+        &self,
+        variables: &[&Variable<VR, DV>],
+    ) -> Vec<Expr<VR, DV>> {
+        let targets = variables
+            .iter()
+            .map(|v| Rc::new(v.read()) as Rc<dyn ExprNode>)
+            .collect();
+        let gradients = grad::gradients(self, targets);
+
+        variables
+            .iter()
+            .enumerate()
+            .map(|(index, v)| grad::gradient(&gradients, index, v.read()))
+            .collect()
+    }
+
+    /// The gradients of `self` with respect to a variable or a tuple of variables of any ranks
+    /// and data types, e.g. `loss.gradients_of((&weights, &bias))`. All of them come from one
+    /// gradient computation.
+    pub fn gradients_of<T: GradientTargets>(&self, targets: T) -> T::Gradients {
+        let gradients = grad::gradients(self, targets.targets());
+        let mut index = 0;
+
+        targets.select(&gradients, &mut index)
+    }
 
-    fn div(self, rhs: Expr<RANK, D>) -> Expr<RANK, D> {
+    fn unary(self, function: fn1::TFFunction1) -> Expr<R, D> {
         let data_type = self.0.data_type();
 
-        Expr(Rc::new(binop::BinOpExpr {
+        Expr(Rc::new(fn1::Fn1Expr {
             id: get_id(),
-            op: binop::BinaryOperator::Div,
-            left: self.clone(),
-            right: rhs.clone(),
+            function,
+            arg: self,
             data_type,
         }))
     }
 }
 
+impl<const R: usize, D: FloatingData<R> + 'static> Neg for Expr<R, D> {
+    type Output = Expr<R, D>;
+
+    fn neg(self) -> Expr<R, D> {
+        self.unary(fn1::TFFunction1::Neg)
+    }
+}
+
+impl<const R: usize, D: FloatingData<R> + RankedData<R>> Expr<R, D> {
+    /// Gaussian error linear unit, `x * (1 + erf(x / sqrt(2))) / 2`.
+    pub fn gelu(self) -> Expr<R, D> {
+        let frac_1_sqrt_2 = D::Element::from_f64(std::f64::consts::FRAC_1_SQRT_2);
+        let cdf = self
+            .clone()
+            .with_scalar(binop::BinaryOperator::Mul, frac_1_sqrt_2)
+            .erf()
+            .with_scalar(binop::BinaryOperator::Add, D::Element::from_f64(1.0))
+            .with_scalar(binop::BinaryOperator::Mul, D::Element::from_f64(0.5));
+
+        self * cdf
+    }
+
+    /// Elementwise power. The exponent is broadcast against `self` and may have a lower rank.
+    pub fn pow<const RE: usize, DE: Data<RE, Element = D::Element> + 'static>(
+        self,
+        exponent: Expr<RE, DE>,
+    ) -> Expr<R, D> {
+        const {
+            assert!(RE <= R, "the exponent can't have a higher rank than the base");
+        }
+
+        broadcast(binop::BinaryOperator::Pow, self, exponent).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn pow_scalar(self, exponent: D::Element) -> Expr<R, D> {
+        self.with_scalar(binop::BinaryOperator::Pow, exponent)
+    }
+
+    fn with_scalar(self, op: binop::BinaryOperator, value: D::Element) -> Expr<R, D> {
+        broadcast(op, self, literal::<R, D>(value)).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
 impl<const R: usize> Expr<R, FloatData<R>> {
-    pub fn tanh(self) -> Expr<R, FloatData<R>> {
-        let data_type = self.0.data_type();
+    pub fn minimize(self, vars: &[VariableRef]) -> Expr<0, NoData> {
+        self.minimize_with(AdaDelta::new(), vars)
+    }
+}
+
+impl<const R: usize, D: RankedData<R> + NumericData<R>> Expr<R, D> {
+    pub fn reduce_sum<const AXIS: usize, const OUT: usize>(self) -> Expr<OUT, D::WithRank<OUT>> {
+        self.reduce::<AXIS, OUT>(fn1::Reduction::Sum)
+    }
+
+    pub fn reduce_mean<const AXIS: usize, const OUT: usize>(self) -> Expr<OUT, D::WithRank<OUT>> {
+        self.reduce::<AXIS, OUT>(fn1::Reduction::Mean)
+    }
+
+    pub fn reduce_max<const AXIS: usize, const OUT: usize>(self) -> Expr<OUT, D::WithRank<OUT>> {
+        self.reduce::<AXIS, OUT>(fn1::Reduction::Max)
+    }
+
+    pub fn reduce_min<const AXIS: usize, const OUT: usize>(self) -> Expr<OUT, D::WithRank<OUT>> {
+        self.reduce::<AXIS, OUT>(fn1::Reduction::Min)
+    }
+
+    pub fn reduce_prod<const AXIS: usize, const OUT: usize>(self) -> Expr<OUT, D::WithRank<OUT>> {
+        self.reduce::<AXIS, OUT>(fn1::Reduction::Prod)
+    }
+
+    pub fn reduce_sum_all(self) -> Expr<0, D::WithRank<0>> {
+        self.reduce_all(fn1::Reduction::Sum)
+    }
+
+    pub fn reduce_mean_all(self) -> Expr<0, D::WithRank<0>> {
+        self.reduce_all(fn1::Reduction::Mean)
+    }
+
+    pub fn reduce_max_all(self) -> Expr<0, D::WithRank<0>> {
+        self.reduce_all(fn1::Reduction::Max)
+    }
+
+    pub fn reduce_min_all(self) -> Expr<0, D::WithRank<0>> {
+        self.reduce_all(fn1::Reduction::Min)
+    }
+
+    pub fn reduce_prod_all(self) -> Expr<0, D::WithRank<0>> {
+        self.reduce_all(fn1::Reduction::Prod)
+    }
+
+    fn reduce<const AXIS: usize, const OUT: usize>(
+        self,
+        reduction: fn1::Reduction,
+    ) -> Expr<OUT, D::WithRank<OUT>> {
+        const {
+            assert!(AXIS < R, "reduction axis is out of range");
+            assert!(OUT + 1 == R, "a reduction removes exactly one axis");
+        }
+
+        let dimensions = self.0.dims();
+        let kept = dimensions.iter().enumerate().filter(|(i, _)| *i != AXIS);
+        let mut shape = [Dim::Fixed(1); OUT];
+
+        for (slot, (_, dimension)) in shape.iter_mut().zip(kept) {
+            *slot = *dimension;
+        }
 
         Expr(Rc::new(fn1::Fn1Expr {
             id: get_id(),
-            function: fn1::TFFunction1::Tanh,
+            function: fn1::TFFunction1::Reduce(reduction, Some(AXIS)),
             arg: self,
-            data_type,
+            data_type: D::with_dimensions(shape),
         }))
     }
 
-    pub fn exp(self) -> Expr<R, FloatData<R>> {
-        let data_type = self.0.data_type();
+    fn reduce_all(self, reduction: fn1::Reduction) -> Expr<0, D::WithRank<0>> {
+        Expr(Rc::new(fn1::Fn1Expr {
+            id: get_id(),
+            function: fn1::TFFunction1::Reduce(reduction, None),
+            arg: self,
+            data_type: D::with_dimensions([]),
+        }))
+    }
+}
+
+impl<const R: usize, D: RankedData<R>> Expr<R, D> {
+    pub fn reshape<const N: usize>(self, shape: [usize; N]) -> Expr<N, D::WithRank<N>> {
+        self.try_reshape(shape).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Reshapes to `shape`. The element count is checked when all dimensions of `self` are
+    /// known, otherwise only when the graph runs.
+    pub fn try_reshape<const N: usize>(
+        self,
+        shape: [usize; N],
+    ) -> Result<Expr<N, D::WithRank<N>>, ShapeError> {
+        let dimensions = self.0.dims();
+        let new_dimensions: Vec<Dim> = shape.iter().map(|d| Dim::Fixed(*d)).collect();
+        let count: Option<usize> = dimensions.iter().map(|d| d.size()).product();
+
+        if count.is_some_and(|count| count != shape.iter().product::<usize>()) {
+            return Err(ShapeError::new("reshape", dimensions, new_dimensions));
+        }
+
+        Ok(Expr(Rc::new(fn1::Fn1Expr {
+            id: get_id(),
+            function: fn1::TFFunction1::Reshape,
+            arg: self,
+            data_type: D::with_dimensions(shape.map(Dim::Fixed)),
+        })))
+    }
+
+    pub fn transpose(self, permutation: [usize; R]) -> Expr<R, D> {
+        self.try_transpose(permutation).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Permutes the axes, axis `i` of the result is axis `permutation[i]` of `self`. Fails
+    /// unless `permutation` contains every axis exactly once; the error holds the permutation
+    /// as its right operand.
+    pub fn try_transpose(self, permutation: [usize; R]) -> Result<Expr<R, D>, ShapeError> {
+        let dimensions = self.0.dims();
+        let mut seen = [false; R];
+        let mut shape = [Dim::Fixed(1); R];
+
+        for (slot, axis) in shape.iter_mut().zip(permutation.iter()) {
+            if *axis >= R || seen[*axis] {
+                let axes = permutation.iter().map(|a| Dim::Fixed(*a)).collect();
+                return Err(ShapeError::new("transpose", dimensions, axes));
+            }
+
+            seen[*axis] = true;
+            *slot = dimensions[*axis];
+        }
+
+        Ok(Expr(Rc::new(fn1::Fn1Expr {
+            id: get_id(),
+            function: fn1::TFFunction1::Transpose(permutation.to_vec()),
+            arg: self,
+            data_type: D::from(shape),
+        })))
+    }
+
+    pub fn expand_dims<const AXIS: usize, const OUT: usize>(self) -> Expr<OUT, D::WithRank<OUT>> {
+        const {
+            assert!(AXIS <= R, "expand_dims axis is out of range");
+            assert!(OUT == R + 1, "expand_dims adds exactly one axis");
+        }
+
+        let mut dimensions = self.0.dims();
+        dimensions.insert(AXIS, Dim::Fixed(1));
+
+        let mut shape = [Dim::Fixed(1); OUT];
+        shape.copy_from_slice(&dimensions);
 
         Expr(Rc::new(fn1::Fn1Expr {
             id: get_id(),
-            function: fn1::TFFunction1::Exp,
+            function: fn1::TFFunction1::ExpandDims(AXIS),
             arg: self,
-            data_type,
+            data_type: D::with_dimensions(shape),
         }))
     }
 
-    pub fn minimize(self, vars: &[VariableRef]) -> Expr<0, NoData> {
-        let mut variables = Vec::new();
+    pub fn squeeze<const AXIS: usize, const OUT: usize>(self) -> Expr<OUT, D::WithRank<OUT>> {
+        const {
+            assert!(AXIS < R, "squeeze axis is out of range");
+            assert!(OUT + 1 == R, "squeeze removes exactly one axis");
+        }
+
+        let mut dimensions = self.0.dims();
+
+        assert!(
+            dimensions[AXIS].is_compatible_with(&Dim::Fixed(1)),
+            "cannot squeeze axis {} of {:?}: dimension is not 1",
+            AXIS,
+            dimensions
+        );
 
-        variables.extend_from_slice(vars);
+        dimensions.remove(AXIS);
 
-        Expr(Rc::new(optimize::AdaDeltaMinimizeExpr::<
-            R,
-            FloatData<R>,
-            FloatData<0>,
-        > {
+        let mut shape = [Dim::Fixed(1); OUT];
+        shape.copy_from_slice(&dimensions);
+
+        Expr(Rc::new(fn1::Fn1Expr {
             id: get_id(),
-            loss: self.clone(),
-            variables,
-            learning_rate: None,
-            rho: None,
-            epsilon: None,
+            function: fn1::TFFunction1::Squeeze(AXIS),
+            arg: self,
+            data_type: D::with_dimensions(shape),
         }))
     }
 }
 
 impl Expr<2, FloatData<2>> {
     pub fn mat_mul(self, other: Expr<2, FloatData<2>>) -> Expr<2, FloatData<2>> {
-        let shape_self = self.0.dimensions();
-        let shape_other = other.0.dimensions();
+        self.try_mat_mul(other).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_mat_mul(
+        self,
+        other: Expr<2, FloatData<2>>,
+    ) -> Result<Expr<2, FloatData<2>>, ShapeError> {
+        let shape_self = self.0.dims();
+        let shape_other = other.0.dims();
+
+        if !shape_self[1].is_compatible_with(&shape_other[0]) {
+            return Err(ShapeError::new("mat_mul", shape_self, shape_other));
+        }
 
         let data_type: FloatData<2> = [shape_self[0], shape_other[1]].into();
 
-        Expr(Rc::new(fn2::Fn2Expr {
+        Ok(Expr(Rc::new(fn2::Fn2Expr {
             id: get_id(),
             function: fn2::TFFunction2::MatMul,
-            arg1: self, 
+            arg1: self,
             arg2: other,
             data_type,
-        }))
+        })))
     }
 }
 
@@ -191,11 +772,11 @@ pub fn vector<D: Data<1> + From<[usize; 1]> + 'static>(v: &[D::Element]) -> Expr
     }))
 }
 
-pub fn float_variable<const D: usize, S: Into<FloatData<D>>>(
+pub fn variable<const RANK: usize, D: Data<RANK>, S: Into<D>>(
     name: &str,
-    initial_value: Expr<D, FloatData<D>>,
+    initial_value: Expr<RANK, D>,
     shape: S,
-) -> Variable<D, FloatData<D>> {
+) -> Variable<RANK, D> {
     Variable {
         id: get_id(),
         name: name.to_string(),
@@ -204,10 +785,10 @@ pub fn float_variable<const D: usize, S: Into<FloatData<D>>>(
     }
 }
 
-pub fn float_feed<const D: usize, S: Into<FloatData<D>>>(
+pub fn feed<const RANK: usize, D: Data<RANK>, S: Into<D>>(
     name: &str,
     shape: S,
-) -> Placeholder<D, FloatData<D>> {
+) -> Placeholder<RANK, D> {
     Placeholder {
         id: get_id(),
         name: name.to_string(),
@@ -215,20 +796,196 @@ pub fn float_feed<const D: usize, S: Into<FloatData<D>>>(
     }
 }
 
+macro_rules! typed_constructors {
+    ($data:ident, $variable:ident, $feed:ident) => {
+        pub fn $variable<const D: usize, S: Into<$data<D>>>(
+            name: &str,
+            initial_value: Expr<D, $data<D>>,
+            shape: S,
+        ) -> Variable<D, $data<D>> {
+            variable(name, initial_value, shape)
+        }
+
+        pub fn $feed<const D: usize, S: Into<$data<D>>>(
+            name: &str,
+            shape: S,
+        ) -> Placeholder<D, $data<D>> {
+            feed(name, shape)
+        }
+    };
+}
+
+typed_constructors!(FloatData, float_variable, float_feed);
+typed_constructors!(DoubleData, double_variable, double_feed);
+typed_constructors!(Int32Data, int32_variable, int32_feed);
+typed_constructors!(Int64Data, int64_variable, int64_feed);
+typed_constructors!(UInt8Data, uint8_variable, uint8_feed);
+typed_constructors!(BoolData, bool_variable, bool_feed);
+
+/// Uniform random values in `[0, 1)`. Panics unless the dimensions are fully defined.
 pub fn random_uniform<const D: usize, S: Into<FloatData<D>>>(shape: S) -> Expr<D, FloatData<D>> {
-    Expr(Rc::new(fn0::Fn0Expr {
-        id: get_id(),
-        function: fn0::TFFunction0::RandomUniform,
-        data_type: shape.into(),
-    }))
+    random(fn0::TFFunction0::RandomUniform, shape.into())
 }
 
+/// Normally distributed random values. Panics unless the dimensions are fully defined.
 pub fn random_standard_normal<const D: usize, S: Into<FloatData<D>>>(
     shape: S,
 ) -> Expr<D, FloatData<D>> {
+    random(fn0::TFFunction0::RandomStandardNormal, shape.into())
+}
+
+fn random<const D: usize>(
+    function: fn0::TFFunction0,
+    data_type: FloatData<D>,
+) -> Expr<D, FloatData<D>> {
+    if !data_type.is_fully_defined() {
+        panic!("{}", ShapeError::new(function.name(), data_type.dims(), Vec::new()));
+    }
+
     Expr(Rc::new(fn0::Fn0Expr {
         id: get_id(),
-        function: fn0::TFFunction0::RandomStandardNormal,
-        data_type: shape.into(),
+        function,
+        data_type,
     }))
 }
+
+/// The gradient of `y` with respect to `x`, with the type and shape of `x`. A non-scalar `y` is
+/// summed first, and the gradient is zero if `y` does not depend on `x`.
+pub fn grad<
+    const YR: usize,
+    DY: FloatingData<YR> + 'static,
+    const XR: usize,
+    DX: FloatingData<XR> + 'static,
+>(
+    y: &Expr<YR, DY>,
+    x: &Expr<XR, DX>,
+) -> Expr<XR, DX> {
+    let gradients = grad::gradients(y, vec![Rc::new(x.clone()) as Rc<dyn ExprNode>]);
+
+    grad::gradient(&gradients, 0, x.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floats<const RANK: usize>(shape: [usize; RANK]) -> Expr<RANK, FloatData<RANK>> {
+        float_feed("x", shape).read()
+    }
+
+    fn fixed(dimensions: &[usize]) -> Vec<Dim> {
+        dimensions.iter().map(|d| Dim::Fixed(*d)).collect()
+    }
+
+    #[test]
+    fn try_mat_mul_reports_both_shapes() {
+        let error = floats([2, 3]).try_mat_mul(floats([2, 2])).err().unwrap();
+
+        assert_eq!(error.operation(), "mat_mul");
+        assert_eq!(error.left(), fixed(&[2, 3]));
+        assert_eq!(error.right(), fixed(&[2, 2]));
+        assert_eq!(error.to_string(), "mat_mul: incompatible shapes [2, 3] and [2, 2]");
+    }
+
+    #[test]
+    #[should_panic(expected = "mat_mul: incompatible shapes [2, 3] and [2, 2]")]
+    fn mat_mul_panics_with_the_shape_error() {
+        floats([2, 3]).mat_mul(floats([2, 2]));
+    }
+
+    #[test]
+    fn try_add_reports_both_shapes() {
+        let error = floats([2, 3]).try_add(floats([3, 2])).err().unwrap();
+
+        assert_eq!(error.operation(), "add");
+        assert_eq!(error.left(), fixed(&[2, 3]));
+        assert_eq!(error.right(), fixed(&[3, 2]));
+        assert_eq!(error.to_string(), "add: incompatible shapes [2, 3] and [3, 2]");
+    }
+
+    #[test]
+    #[should_panic(expected = "add: incompatible shapes [2, 3] and [3, 2]")]
+    fn add_panics_with_the_shape_error() {
+        let _ = floats([2, 3]) + floats([3, 2]);
+    }
+
+    #[test]
+    fn reductions_remove_the_axis() {
+        assert_eq!(floats([2, 3, 4]).reduce_sum::<0, 2>().dims(), fixed(&[3, 4]));
+        assert_eq!(floats([2, 3, 4]).reduce_mean::<1, 2>().dims(), fixed(&[2, 4]));
+        assert_eq!(floats([2, 3, 4]).reduce_max::<2, 2>().dims(), fixed(&[2, 3]));
+        assert_eq!(floats([2, 3]).reduce_min::<1, 1>().dims(), fixed(&[2]));
+        assert_eq!(floats([5]).reduce_prod::<0, 0>().dims(), fixed(&[]));
+        assert_eq!(floats([2, 3]).reduce_sum_all().dims(), fixed(&[]));
+    }
+
+    #[test]
+    fn reshape_dimensions() {
+        assert_eq!(floats([2, 3]).reshape([3, 2]).dims(), fixed(&[3, 2]));
+        assert_eq!(floats([2, 3]).reshape([6]).dims(), fixed(&[6]));
+        assert_eq!(floats([2, 3, 4]).reshape([4, 6]).dims(), fixed(&[4, 6]));
+    }
+
+    #[test]
+    fn try_reshape_rejects_a_count_mismatch() {
+        let error = floats([2, 3]).try_reshape([4, 2]).err().unwrap();
+
+        assert_eq!(error.operation(), "reshape");
+        assert_eq!(error.left(), fixed(&[2, 3]));
+        assert_eq!(error.right(), fixed(&[4, 2]));
+    }
+
+    #[test]
+    fn transpose_dimensions() {
+        assert_eq!(floats([2, 3]).transpose([1, 0]).dims(), fixed(&[3, 2]));
+        assert_eq!(floats([2, 3, 4]).transpose([2, 0, 1]).dims(), fixed(&[4, 2, 3]));
+    }
+
+    #[test]
+    fn try_transpose_rejects_a_bad_permutation() {
+        let error = floats([2, 3, 4]).try_transpose([0, 0, 1]).err().unwrap();
+
+        assert_eq!(error.operation(), "transpose");
+        assert_eq!(error.left(), fixed(&[2, 3, 4]));
+        assert_eq!(error.right(), fixed(&[0, 0, 1]));
+        assert!(floats([2, 3]).try_transpose([0, 2]).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "transpose: incompatible shapes [2, 3] and [0, 2]")]
+    fn transpose_panics_on_a_bad_permutation() {
+        floats([2, 3]).transpose([0, 2]);
+    }
+
+    #[test]
+    fn expand_dims_and_squeeze_dimensions() {
+        assert_eq!(floats([2, 3]).expand_dims::<0, 3>().dims(), fixed(&[1, 2, 3]));
+        assert_eq!(floats([2, 3]).expand_dims::<1, 3>().dims(), fixed(&[2, 1, 3]));
+        assert_eq!(floats([2, 3]).expand_dims::<2, 3>().dims(), fixed(&[2, 3, 1]));
+        assert_eq!(floats([2, 1, 3]).squeeze::<1, 2>().dims(), fixed(&[2, 3]));
+    }
+
+    #[test]
+    fn dynamic_dimensions_propagate() {
+        let batch = || float_feed("x", [Dim::Dynamic, Dim::Fixed(2)]).read();
+
+        assert_eq!(
+            batch().mat_mul(floats([2, 4])).dims(),
+            vec![Dim::Dynamic, Dim::Fixed(4)]
+        );
+        assert_eq!((batch() + batch()).dims(), vec![Dim::Dynamic, Dim::Fixed(2)]);
+        assert_eq!(batch().reduce_sum::<1, 1>().dims(), vec![Dim::Dynamic]);
+        assert_eq!(batch().reduce_mean::<0, 1>().dims(), fixed(&[2]));
+        assert_eq!(
+            batch().mat_mul(floats([2, 4])).reduce_max::<1, 1>().dims(),
+            vec![Dim::Dynamic]
+        );
+    }
+
+    #[test]
+    fn dynamic_dimensions_are_compatible_with_any_size() {
+        let batch = float_feed("x", [Dim::Fixed(3), Dim::Dynamic]).read();
+
+        assert_eq!(batch.mat_mul(floats([2, 4])).dims(), fixed(&[3, 4]));
+    }
+}
@@ -0,0 +1,69 @@
+use super::fn1::Reduction;
+
+/// The operation of an expression node, independent of any backend, see `ExprNode::op`. The
+/// operands are the inputs of the node in order; the result has the element type and the
+/// dimensions of the node.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op {
+    /// A value fed at run time, by name.
+    Placeholder(String),
+    /// A variable, by name. Its input is the initial value.
+    Variable(String),
+    /// A constant, with its values in row-major order. The values are converted to `f64`, so
+    /// `i64` values beyond 2^53 are rounded.
+    Constant(Vec<f64>),
+    /// An output of an imported graph, by its name `operation:index`.
+    Imported(String),
+    RandomUniform,
+    RandomStandardNormal,
+    /// Elementwise arithmetic under NumPy broadcasting rules. Integer division truncates.
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Tanh,
+    Exp,
+    Neg,
+    Log,
+    Log1p,
+    Sqrt,
+    Rsqrt,
+    Square,
+    Abs,
+    Sign,
+    Sin,
+    Cos,
+    Floor,
+    Ceil,
+    /// Rounds half to even.
+    Round,
+    Reciprocal,
+    Erf,
+    Relu,
+    Sigmoid,
+    Softplus,
+    Elu,
+    /// Softmax over the last axis.
+    Softmax,
+    /// Log-softmax over the last axis.
+    LogSoftmax,
+    /// Reduces along a single axis, or along all axes when the axis is `None`.
+    Reduce(Reduction, Option<usize>),
+    /// Reshapes to the dimensions of the node, where a dynamic dimension takes the remaining
+    /// elements.
+    Reshape,
+    Transpose(Vec<usize>),
+    ExpandDims(usize),
+    Squeeze(usize),
+    /// The same values, e.g. under a data type with other static dimensions.
+    Identity,
+    MatMul,
+    /// The gradients of the first input with respect to each of the other inputs, computed
+    /// together and read with `Gradient`.
+    Gradients,
+    /// The gradient at an index of the input, which is a `Gradients` node.
+    Gradient(usize),
+    /// An optimizer step minimizing the input.
+    Minimize,
+}
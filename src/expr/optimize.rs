@@ -1,26 +1,559 @@
-use super::{Expr, ExprImpl, Id};
+use super::{Expr, ExprImpl, ExprNode, Id, Op};
 use crate::compiler::{CompiledElement, Compiler};
 use crate::data::*;
 use crate::expr::variable::VariableRef;
 use crate::tensorflow::train::Optimizer;
+use crate::train::{scalar_constant, MomentumOptimizer, RmsPropOptimizer};
+use tensorflow::ops;
 use tensorflow::train::AdadeltaOptimizer;
+use tensorflow::train::AdamOptimizer;
+use tensorflow::train::ApplyGradientsOptions;
+use tensorflow::train::GradientDescentOptimizer;
 use tensorflow::train::MinimizeOptions;
+use tensorflow::DataType;
+use tensorflow::Operation;
+use tensorflow::Output;
+use tensorflow::Scope;
 use tensorflow::Shape;
 use tensorflow::Status;
+use tensorflow::Variable;
 
-// TODO: GradientDescent, SdcaOptimizer, SdcaOptimizerV2
+// TODO: SdcaOptimizer, SdcaOptimizerV2
 
-pub(crate) struct AdaDeltaMinimizeExpr<const RANK: usize, D: Data<RANK>, SD: ScalarData> {
+/// Hyperparameters of an optimizer, used with [`Expr::minimize_with`]. Hyperparameters are
+/// scalar expressions, so they can be constants as well as placeholders fed at every step.
+/// Their element type must match that of the loss, e.g. `Adam::<DoubleData<0>>::default()`
+/// for a `DoubleData` loss.
+pub trait OptimizerConfig {
+    /// The element type of the hyperparameters and of the loss.
+    type Element: ElementType;
+
+    /// Builds the optimizer for a loss of the given data type.
+    fn build(
+        &self,
+        compiler: &mut Compiler,
+        data_type: DataType,
+    ) -> Result<Box<dyn Optimizer>, Status>;
+
+    /// Transforms applied to the gradients before the updates, in order.
+    fn gradient_transforms(&self) -> Vec<GradientTransform> {
+        Vec::new()
+    }
+
+    /// Applies `transform` to the gradients after the transforms already configured, e.g.
+    /// `Adam::new().transform_gradients(GradientTransform::ClipByGlobalNorm(5.0))`.
+    fn transform_gradients(self, transform: GradientTransform) -> TransformedOptimizer<Self>
+    where
+        Self: Sized,
+    {
+        TransformedOptimizer {
+            optimizer: self,
+            transform,
+        }
+    }
+
+    /// Minimizes `loss` with respect to `vars`, same as `loss.minimize_with(self, vars)`.
+    fn minimize<const RANK: usize, D: FloatingData<RANK, Element = Self::Element> + 'static>(
+        self,
+        loss: Expr<RANK, D>,
+        vars: &[VariableRef],
+    ) -> Expr<0, NoData>
+    where
+        Self: Sized + 'static,
+    {
+        loss.minimize_with(self, vars)
+    }
+}
+
+fn hyperparameter<SD: ScalarData>(
+    compiler: &mut Compiler,
+    value: &Option<Expr<0, SD>>,
+    default: f64,
+    data_type: DataType,
+) -> Result<Output, Status> {
+    match value {
+        Some(value) => compiler.get_output(value),
+        None => Ok(scalar_constant(default, data_type, compiler.borrow_scope_mut())?.output(0)),
+    }
+}
+
+/// A transformation of the gradients between computing them and applying the updates.
+#[derive(Clone, Copy, Debug)]
+pub enum GradientTransform {
+    /// Clips every element to `[min, max]`.
+    ClipByValue(f64, f64),
+    /// Rescales every gradient whose L2 norm exceeds the given norm down to it.
+    ClipByNorm(f64),
+    /// Rescales all gradients together so that their joint L2 norm doesn't exceed the given
+    /// norm, as in `tf.clip_by_global_norm`.
+    ClipByGlobalNorm(f64),
+    /// Multiplies every gradient by a factor.
+    Scale(f64),
+}
+
+impl GradientTransform {
+    fn apply(
+        &self,
+        scope: &mut Scope,
+        grads_and_vars: Vec<(Option<Output>, Variable)>,
+    ) -> Result<Vec<(Option<Output>, Variable)>, Status> {
+        let transform = match *self {
+            GradientTransform::ClipByValue(min, max) => TensorTransform::ClipByValue(min, max),
+            GradientTransform::ClipByNorm(clip_norm) => TensorTransform::ClipByNorm(clip_norm),
+            GradientTransform::ClipByGlobalNorm(clip_norm) => {
+                return clip_by_global_norm(scope, grads_and_vars, clip_norm);
+            }
+            GradientTransform::Scale(factor) => TensorTransform::Scale(factor),
+        };
+
+        let mut transformed = Vec::with_capacity(grads_and_vars.len());
+
+        for (grad, var) in grads_and_vars {
+            let grad = match grad {
+                Some(grad) => Some(transform.apply(scope, grad, var.data_type())?.output(0)),
+                None => None,
+            };
+
+            transformed.push((grad, var));
+        }
+
+        Ok(transformed)
+    }
+}
+
+/// A gradient transform that applies to every gradient on its own.
+enum TensorTransform {
+    ClipByValue(f64, f64),
+    ClipByNorm(f64),
+    Scale(f64),
+}
+
+impl TensorTransform {
+    fn apply(
+        &self,
+        scope: &mut Scope,
+        grad: Output,
+        data_type: DataType,
+    ) -> Result<Operation, Status> {
+        match *self {
+            TensorTransform::ClipByValue(min, max) => {
+                let min = scalar_constant(min, data_type, scope)?;
+                let max = scalar_constant(max, data_type, scope)?;
+                ops::clip_by_value(grad, min, max, scope)
+            }
+            TensorTransform::ClipByNorm(clip_norm) => {
+                let norm = ops::sqrt(l2_norm_squared(scope, grad.clone())?, scope)?;
+                let clip_norm = scalar_constant(clip_norm, data_type, scope)?;
+                let scale = clip_scale(scope, norm.output(0), clip_norm.output(0))?;
+                ops::mul(grad, scale, scope)
+            }
+            TensorTransform::Scale(factor) => {
+                let factor = scalar_constant(factor, data_type, scope)?;
+                ops::mul(grad, factor, scope)
+            }
+        }
+    }
+}
+
+fn clip_by_global_norm(
+    scope: &mut Scope,
+    grads_and_vars: Vec<(Option<Output>, Variable)>,
+    clip_norm: f64,
+) -> Result<Vec<(Option<Output>, Variable)>, Status> {
+    let mut sum: Option<Output> = None;
+    let mut data_type = DataType::Float;
+
+    for (grad, var) in &grads_and_vars {
+        if let Some(grad) = grad {
+            let squared = l2_norm_squared(scope, grad.clone())?.output(0);
+            data_type = var.data_type();
+
+            sum = Some(match sum {
+                Some(sum) => ops::add(sum, squared, scope)?.output(0),
+                None => squared,
+            });
+        }
+    }
+
+    let sum = match sum {
+        Some(sum) => sum,
+        None => return Ok(grads_and_vars),
+    };
+
+    let global_norm = ops::sqrt(sum, scope)?;
+    let clip_norm = scalar_constant(clip_norm, data_type, scope)?;
+    let scale = clip_scale(scope, global_norm.output(0), clip_norm.output(0))?;
+
+    let mut transformed = Vec::with_capacity(grads_and_vars.len());
+
+    for (grad, var) in grads_and_vars {
+        let grad = match grad {
+            Some(grad) => Some(ops::mul(grad, scale.clone(), scope)?.output(0)),
+            None => None,
+        };
+
+        transformed.push((grad, var));
+    }
+
+    Ok(transformed)
+}
+
+/// The sum of the squares of all elements of `tensor`, whatever its rank.
+fn l2_norm_squared(scope: &mut Scope, tensor: Output) -> Result<Operation, Status> {
+    let flat_shape = ops::constant(&[-1_i64][..], scope)?;
+    let axis = ops::constant(&[0_i32][..], scope)?;
+
+    let squared = ops::square(tensor, scope)?;
+    let flat = ops::reshape(squared, flat_shape, scope)?;
+    ops::sum(flat, axis, scope)
+}
+
+/// `clip_norm / max(norm, clip_norm)`, i.e. 1 unless the norm exceeds `clip_norm`.
+fn clip_scale(scope: &mut Scope, norm: Output, clip_norm: Output) -> Result<Output, Status> {
+    let maximum = ops::maximum(norm, clip_norm.clone(), scope)?;
+    Ok(ops::div(clip_norm, maximum, scope)?.output(0))
+}
+
+/// An optimizer that transforms the gradients before applying them, see
+/// [`OptimizerConfig::transform_gradients`].
+#[derive(Clone)]
+pub struct TransformedOptimizer<O: OptimizerConfig> {
+    optimizer: O,
+    transform: GradientTransform,
+}
+
+impl<O: OptimizerConfig> OptimizerConfig for TransformedOptimizer<O> {
+    type Element = O::Element;
+
+    fn build(
+        &self,
+        compiler: &mut Compiler,
+        data_type: DataType,
+    ) -> Result<Box<dyn Optimizer>, Status> {
+        self.optimizer.build(compiler, data_type)
+    }
+
+    fn gradient_transforms(&self) -> Vec<GradientTransform> {
+        let mut transforms = self.optimizer.gradient_transforms();
+        transforms.push(self.transform);
+        transforms
+    }
+}
+
+/// AdaDelta. Hyperparameters that are not set default to the values of the `tensorflow`
+/// crate, a learning rate of 0.001, a rho of 0.95 and an epsilon of 1e-8.
+#[derive(Clone)]
+pub struct AdaDelta<SD: ScalarData = FloatData<0>> {
+    learning_rate: Option<Expr<0, SD>>,
+    rho: Option<Expr<0, SD>>,
+    epsilon: Option<Expr<0, SD>>,
+}
+
+impl AdaDelta {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<SD: ScalarData> Default for AdaDelta<SD> {
+    fn default() -> Self {
+        Self {
+            learning_rate: None,
+            rho: None,
+            epsilon: None,
+        }
+    }
+}
+
+impl<SD: ScalarData> AdaDelta<SD> {
+    pub fn learning_rate<L: Into<Expr<0, SD>>>(mut self, learning_rate: L) -> Self {
+        self.learning_rate = Some(learning_rate.into());
+        self
+    }
+
+    pub fn rho<R: Into<Expr<0, SD>>>(mut self, rho: R) -> Self {
+        self.rho = Some(rho.into());
+        self
+    }
+
+    pub fn epsilon<E: Into<Expr<0, SD>>>(mut self, epsilon: E) -> Self {
+        self.epsilon = Some(epsilon.into());
+        self
+    }
+}
+
+impl<SD: ScalarData> OptimizerConfig for AdaDelta<SD> {
+    type Element = SD::Element;
+
+    fn build(
+        &self,
+        compiler: &mut Compiler,
+        data_type: DataType,
+    ) -> Result<Box<dyn Optimizer>, Status> {
+        let learning_rate = hyperparameter(compiler, &self.learning_rate, 0.001, data_type)?;
+        let rho = hyperparameter(compiler, &self.rho, 0.95, data_type)?;
+        let epsilon = hyperparameter(compiler, &self.epsilon, 1e-8, data_type)?;
+
+        let mut optimizer = AdadeltaOptimizer::new();
+        optimizer.set_learning_rate(learning_rate);
+        optimizer.set_rho(rho);
+        optimizer.set_epsilon(epsilon);
+
+        Ok(Box::new(optimizer))
+    }
+}
+
+/// Plain stochastic gradient descent.
+#[derive(Clone)]
+pub struct GradientDescent<SD: ScalarData = FloatData<0>> {
+    learning_rate: Option<Expr<0, SD>>,
+}
+
+impl GradientDescent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<SD: ScalarData> Default for GradientDescent<SD> {
+    fn default() -> Self {
+        Self {
+            learning_rate: None,
+        }
+    }
+}
+
+impl<SD: ScalarData> GradientDescent<SD> {
+    pub fn learning_rate<L: Into<Expr<0, SD>>>(mut self, learning_rate: L) -> Self {
+        self.learning_rate = Some(learning_rate.into());
+        self
+    }
+}
+
+impl<SD: ScalarData> OptimizerConfig for GradientDescent<SD> {
+    type Element = SD::Element;
+
+    fn build(
+        &self,
+        compiler: &mut Compiler,
+        data_type: DataType,
+    ) -> Result<Box<dyn Optimizer>, Status> {
+        let learning_rate = hyperparameter(compiler, &self.learning_rate, 0.01, data_type)?;
+
+        Ok(Box::new(GradientDescentOptimizer::new(learning_rate)))
+    }
+}
+
+/// Gradient descent with (optionally Nesterov) momentum.
+#[derive(Clone)]
+pub struct Momentum<SD: ScalarData = FloatData<0>> {
+    learning_rate: Option<Expr<0, SD>>,
+    momentum: Option<Expr<0, SD>>,
+    nesterov: bool,
+}
+
+impl Momentum {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<SD: ScalarData> Default for Momentum<SD> {
+    fn default() -> Self {
+        Self {
+            learning_rate: None,
+            momentum: None,
+            nesterov: false,
+        }
+    }
+}
+
+impl<SD: ScalarData> Momentum<SD> {
+    pub fn learning_rate<L: Into<Expr<0, SD>>>(mut self, learning_rate: L) -> Self {
+        self.learning_rate = Some(learning_rate.into());
+        self
+    }
+
+    pub fn momentum<M: Into<Expr<0, SD>>>(mut self, momentum: M) -> Self {
+        self.momentum = Some(momentum.into());
+        self
+    }
+
+    pub fn nesterov(mut self, nesterov: bool) -> Self {
+        self.nesterov = nesterov;
+        self
+    }
+}
+
+impl<SD: ScalarData> OptimizerConfig for Momentum<SD> {
+    type Element = SD::Element;
+
+    fn build(
+        &self,
+        compiler: &mut Compiler,
+        data_type: DataType,
+    ) -> Result<Box<dyn Optimizer>, Status> {
+        let learning_rate = hyperparameter(compiler, &self.learning_rate, 0.01, data_type)?;
+        let momentum = hyperparameter(compiler, &self.momentum, 0.9, data_type)?;
+
+        let mut optimizer = MomentumOptimizer::new(learning_rate, momentum);
+        optimizer.set_use_nesterov(self.nesterov);
+
+        Ok(Box::new(optimizer))
+    }
+}
+
+/// Adam. Hyperparameters that are not set default to the values of the `tensorflow` crate, a
+/// learning rate of 0.001, a beta1 of 0.9, a beta2 of 0.999 and an epsilon of 1e-8.
+#[derive(Clone)]
+pub struct Adam<SD: ScalarData = FloatData<0>> {
+    learning_rate: Option<Expr<0, SD>>,
+    beta1: Option<Expr<0, SD>>,
+    beta2: Option<Expr<0, SD>>,
+    epsilon: Option<Expr<0, SD>>,
+}
+
+impl Adam {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<SD: ScalarData> Default for Adam<SD> {
+    fn default() -> Self {
+        Self {
+            learning_rate: None,
+            beta1: None,
+            beta2: None,
+            epsilon: None,
+        }
+    }
+}
+
+impl<SD: ScalarData> Adam<SD> {
+    pub fn learning_rate<L: Into<Expr<0, SD>>>(mut self, learning_rate: L) -> Self {
+        self.learning_rate = Some(learning_rate.into());
+        self
+    }
+
+    pub fn beta1<B: Into<Expr<0, SD>>>(mut self, beta1: B) -> Self {
+        self.beta1 = Some(beta1.into());
+        self
+    }
+
+    pub fn beta2<B: Into<Expr<0, SD>>>(mut self, beta2: B) -> Self {
+        self.beta2 = Some(beta2.into());
+        self
+    }
+
+    pub fn epsilon<E: Into<Expr<0, SD>>>(mut self, epsilon: E) -> Self {
+        self.epsilon = Some(epsilon.into());
+        self
+    }
+}
+
+impl<SD: ScalarData> OptimizerConfig for Adam<SD> {
+    type Element = SD::Element;
+
+    fn build(
+        &self,
+        compiler: &mut Compiler,
+        data_type: DataType,
+    ) -> Result<Box<dyn Optimizer>, Status> {
+        let learning_rate = hyperparameter(compiler, &self.learning_rate, 0.001, data_type)?;
+        let beta1 = hyperparameter(compiler, &self.beta1, 0.9, data_type)?;
+        let beta2 = hyperparameter(compiler, &self.beta2, 0.999, data_type)?;
+        let epsilon = hyperparameter(compiler, &self.epsilon, 1e-8, data_type)?;
+
+        let mut optimizer = AdamOptimizer::new();
+        optimizer.set_learning_rate(learning_rate);
+        optimizer.set_beta1(beta1);
+        optimizer.set_beta2(beta2);
+        optimizer.set_epsilon(epsilon);
+
+        Ok(Box::new(optimizer))
+    }
+}
+
+/// RMSProp.
+#[derive(Clone)]
+pub struct RmsProp<SD: ScalarData = FloatData<0>> {
+    learning_rate: Option<Expr<0, SD>>,
+    decay: Option<Expr<0, SD>>,
+    momentum: Option<Expr<0, SD>>,
+    epsilon: Option<Expr<0, SD>>,
+}
+
+impl RmsProp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<SD: ScalarData> Default for RmsProp<SD> {
+    fn default() -> Self {
+        Self {
+            learning_rate: None,
+            decay: None,
+            momentum: None,
+            epsilon: None,
+        }
+    }
+}
+
+impl<SD: ScalarData> RmsProp<SD> {
+    pub fn learning_rate<L: Into<Expr<0, SD>>>(mut self, learning_rate: L) -> Self {
+        self.learning_rate = Some(learning_rate.into());
+        self
+    }
+
+    pub fn decay<R: Into<Expr<0, SD>>>(mut self, decay: R) -> Self {
+        self.decay = Some(decay.into());
+        self
+    }
+
+    pub fn momentum<M: Into<Expr<0, SD>>>(mut self, momentum: M) -> Self {
+        self.momentum = Some(momentum.into());
+        self
+    }
+
+    pub fn epsilon<E: Into<Expr<0, SD>>>(mut self, epsilon: E) -> Self {
+        self.epsilon = Some(epsilon.into());
+        self
+    }
+}
+
+impl<SD: ScalarData> OptimizerConfig for RmsProp<SD> {
+    type Element = SD::Element;
+
+    fn build(
+        &self,
+        compiler: &mut Compiler,
+        data_type: DataType,
+    ) -> Result<Box<dyn Optimizer>, Status> {
+        let learning_rate = hyperparameter(compiler, &self.learning_rate, 0.001, data_type)?;
+        let decay = hyperparameter(compiler, &self.decay, 0.9, data_type)?;
+        let momentum = hyperparameter(compiler, &self.momentum, 0.0, data_type)?;
+        let epsilon = hyperparameter(compiler, &self.epsilon, 1e-7, data_type)?;
+
+        Ok(Box::new(RmsPropOptimizer::new(
+            learning_rate,
+            decay,
+            momentum,
+            epsilon,
+        )))
+    }
+}
+
+pub(crate) struct MinimizeExpr<const RANK: usize, D: Data<RANK>, O: OptimizerConfig> {
     pub(crate) id: Id,
     pub(crate) loss: Expr<RANK, D>,
     pub(crate) variables: Vec<VariableRef>,
-    pub(crate) learning_rate: Option<Expr<0, SD>>,
-    pub(crate) rho: Option<Expr<0, SD>>,
-    pub(crate) epsilon: Option<Expr<0, SD>>,
+    pub(crate) optimizer: O,
 }
 
-impl<const RANK: usize, D: Data<RANK>, SD: ScalarData> ExprImpl<0, NoData>
-    for AdaDeltaMinimizeExpr<RANK, D, SD>
+impl<const RANK: usize, D: Data<RANK>, O: OptimizerConfig> ExprImpl<0, NoData>
+    for MinimizeExpr<RANK, D, O>
 {
     fn id(&self) -> Id {
         self.id
@@ -34,39 +567,51 @@ impl<const RANK: usize, D: Data<RANK>, SD: ScalarData> ExprImpl<0, NoData>
         self.data_type().shape()
     }
 
-    fn dimensions(&self) -> Vec<u64> {
-        self.data_type().dimensions()
+    fn dims(&self) -> Vec<Dim> {
+        self.data_type().dims()
+    }
+
+    fn kind(&self) -> &'static str {
+        "MinimizeExpr"
+    }
+
+    fn name(&self) -> String {
+        "minimize".to_string()
+    }
+
+    fn inputs(&self) -> Vec<&dyn ExprNode> {
+        vec![&self.loss as &dyn ExprNode]
+    }
+
+    fn op(&self) -> Op {
+        Op::Minimize
     }
 
     fn make_operation(&self, compiler: &mut Compiler) -> Result<CompiledElement, Status> {
         let loss_output = compiler.get_output(&self.loss)?;
-
-        let mut optimizer = AdadeltaOptimizer::new();
+        let optimizer = self.optimizer.build(compiler, self.loss.0.data_type().data_type())?;
         let mut variables = Vec::new();
 
         for v in &self.variables {
             variables.push(compiler.variable_by_ref(v)?);
         }
 
-        if let Some(learning_rate) = &self.learning_rate {
-            let learning_rate_output = compiler.get_output(&learning_rate)?;
-            optimizer.set_learning_rate(learning_rate_output);
-        }
-
-        if let Some(rho) = &self.rho {
-            let rho_output = compiler.get_output(&rho)?;
-            optimizer.set_rho(rho_output);
-        }
+        let scope = compiler.borrow_scope_mut();
+        let mut grads_and_vars = optimizer.compute_gradients(
+            scope,
+            loss_output,
+            MinimizeOptions::default().with_variables(&variables),
+        )?;
 
-        if let Some(epsilon) = &self.epsilon {
-            let epsilon_output = compiler.get_output(&epsilon)?;
-            optimizer.set_epsilon(epsilon_output);
+        for transform in self.optimizer.gradient_transforms() {
+            grads_and_vars = transform.apply(scope, grads_and_vars)?;
         }
 
-        let (variables, operation) = optimizer.minimize(
-            compiler.borrow_scope_mut(),
-            loss_output,
-            MinimizeOptions::default().with_variables(&variables),
+        let (variables, operation) = optimizer.apply_gradients(
+            scope,
+            ApplyGradientsOptions {
+                grads_and_vars: &grads_and_vars,
+            },
         )?;
 
         Ok(CompiledElement::Optimizer(operation, variables))
@@ -1,6 +1,7 @@
-use super::{Expr, ExprImpl, Id};
+use super::{describe_leaf, Expr, ExprImpl, ExprNode, Id, Op};
 use crate::compiler::{CompiledElement, Compiler};
 use crate::data::*;
+use std::fmt;
 use std::rc::Rc;
 use tensorflow::ops;
 use tensorflow::Shape;
@@ -26,9 +27,9 @@ impl<const RANK: usize, D: Data<RANK> + 'static> Placeholder<RANK, D> {
     }
 }
 
+#[derive(Clone)]
 pub struct PlaceholderRef<const RANK: usize, D: Data<RANK>> {
     pub(crate) id: Id,
-    #[allow(dead_code)]
     pub(crate) data_type: D,
 }
 
@@ -47,8 +48,28 @@ impl<const RANK: usize, D: Data<RANK>> ExprImpl<RANK, D> for ReadPlaceholderExpr
         self.0.data_type.shape()
     }
 
-    fn dimensions(&self) -> Vec<u64> {
-        self.0.data_type.dimensions()
+    fn dims(&self) -> Vec<Dim> {
+        self.0.data_type.dims()
+    }
+
+    fn kind(&self) -> &'static str {
+        "Placeholder"
+    }
+
+    fn name(&self) -> String {
+        self.0.name.clone()
+    }
+
+    fn inputs(&self) -> Vec<&dyn ExprNode> {
+        Vec::new()
+    }
+
+    fn op(&self) -> Op {
+        Op::Placeholder(self.0.name.clone())
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter, annotate: bool) -> fmt::Result {
+        describe_leaf(f, &self.0.name, &self.dims(), annotate)
     }
 
     fn make_operation(&self, compiler: &mut Compiler) -> Result<CompiledElement, Status> {
@@ -0,0 +1,136 @@
+use super::{binop, fn0, fn1, fn2, get_id, Expr};
+use crate::data::*;
+use crate::error::ShapeError;
+use std::ops::{Add, Div, Mul, Sub};
+use std::rc::Rc;
+
+/// Elementwise arithmetic between expressions of the same static shape. The shapes always
+/// match, so unlike the operators on dynamic data types these never panic.
+macro_rules! static_arithmetic {
+    ($(($trait:ident, $method:ident, $op:ident)),*) => {
+        $(
+            impl<T: ElementType, const RANK: usize, S: StaticShape<RANK>> $trait
+                for Expr<RANK, StaticData<T, S>>
+            where
+                StaticData<T, S>: NumericData<RANK>,
+            {
+                type Output = Expr<RANK, StaticData<T, S>>;
+
+                fn $method(self, rhs: Self) -> Self::Output {
+                    elementwise(binop::BinaryOperator::$op, self, rhs)
+                }
+            }
+        )*
+    };
+}
+
+static_arithmetic!((Add, add, Add), (Sub, sub, Sub), (Mul, mul, Mul), (Div, div, Div));
+
+fn elementwise<T: ElementType, const RANK: usize, S: StaticShape<RANK>>(
+    op: binop::BinaryOperator,
+    left: Expr<RANK, StaticData<T, S>>,
+    right: Expr<RANK, StaticData<T, S>>,
+) -> Expr<RANK, StaticData<T, S>> {
+    Expr(Rc::new(binop::BinOpExpr {
+        id: get_id(),
+        op,
+        left,
+        right,
+        data_type: StaticData::new(),
+    }))
+}
+
+impl<T: ElementType, const M: usize, const K: usize> Expr<2, StaticData<T, Shape2<M, K>>>
+where
+    StaticData<T, Shape2<M, K>>: NumericData<2>,
+{
+    /// Matrix product, `[M, K] x [K, N] -> [M, N]`. Mismatched inner dimensions don't compile.
+    pub fn mat_mul<const N: usize>(
+        self,
+        other: Expr<2, StaticData<T, Shape2<K, N>>>,
+    ) -> Expr<2, StaticData<T, Shape2<M, N>>> {
+        Expr(Rc::new(fn2::Fn2Expr {
+            id: get_id(),
+            function: fn2::TFFunction2::MatMul,
+            arg1: self,
+            arg2: other,
+            data_type: StaticData::new(),
+        }))
+    }
+}
+
+impl<T: ElementType, const RANK: usize, S: StaticShape<RANK>> Expr<RANK, StaticData<T, S>> {
+    /// The same values with runtime checked dimensions, e.g. `into_dynamic::<FloatData<2>>()`,
+    /// for operations that only exist on dynamic data types.
+    pub fn into_dynamic<D: RankedData<RANK, Element = T>>(self) -> Expr<RANK, D> {
+        Expr(Rc::new(fn1::Fn1Expr {
+            id: get_id(),
+            function: fn1::TFFunction1::Identity,
+            arg: self,
+            data_type: D::from(S::DIMS),
+        }))
+    }
+
+    pub fn reduce_sum_all(self) -> Expr<0, StaticData<T, Shape0>>
+    where
+        StaticData<T, S>: NumericData<RANK>,
+    {
+        self.reduce_all(fn1::Reduction::Sum)
+    }
+
+    pub fn reduce_mean_all(self) -> Expr<0, StaticData<T, Shape0>>
+    where
+        StaticData<T, S>: NumericData<RANK>,
+    {
+        self.reduce_all(fn1::Reduction::Mean)
+    }
+
+    fn reduce_all(self, reduction: fn1::Reduction) -> Expr<0, StaticData<T, Shape0>> {
+        Expr(Rc::new(fn1::Fn1Expr {
+            id: get_id(),
+            function: fn1::TFFunction1::Reduce(reduction, None),
+            arg: self,
+            data_type: StaticData::new(),
+        }))
+    }
+}
+
+impl<const RANK: usize, S: StaticShape<RANK>> Expr<RANK, Float<S>> {
+    pub fn random_uniform() -> Self {
+        Expr(Rc::new(fn0::Fn0Expr {
+            id: get_id(),
+            function: fn0::TFFunction0::RandomUniform,
+            data_type: StaticData::new(),
+        }))
+    }
+
+    pub fn random_standard_normal() -> Self {
+        Expr(Rc::new(fn0::Fn0Expr {
+            id: get_id(),
+            function: fn0::TFFunction0::RandomStandardNormal,
+            data_type: StaticData::new(),
+        }))
+    }
+}
+
+impl<const RANK: usize, D: RankedData<RANK>> Expr<RANK, D> {
+    /// The same values with the static shape `S`, e.g. for an imported output. Fails unless
+    /// every dimension is known and equal to that of `S`.
+    pub fn try_into_static<S: StaticShape<RANK>>(
+        self,
+    ) -> Result<Expr<RANK, StaticData<D::Element, S>>, ShapeError> {
+        let dimensions = self.0.dims();
+        let static_dimensions: Vec<Dim> = S::DIMS.iter().map(|d| Dim::Fixed(*d)).collect();
+
+        if dimensions != static_dimensions {
+            return Err(ShapeError::new("into_static", dimensions, static_dimensions));
+        }
+
+        Ok(Expr(Rc::new(fn1::Fn1Expr {
+            id: get_id(),
+            function: fn1::TFFunction1::Identity,
+            arg: self,
+            data_type: StaticData::new(),
+        })))
+    }
+}
@@ -1,6 +1,7 @@
-use super::{Expr, ExprImpl, Id};
+use super::{describe_leaf, Expr, ExprImpl, ExprNode, Id, Op};
 use crate::compiler::{CompiledElement, Compiler};
 use crate::data::*;
+use std::fmt;
 use std::rc::Rc;
 use tensorflow::Shape;
 use tensorflow::Status;
@@ -44,8 +45,28 @@ impl<const RANK: usize, D: Data<RANK>> ExprImpl<RANK, D> for ReadVariableExpr<RA
         self.0.data_type.shape()
     }
 
-    fn dimensions(&self) -> Vec<u64> {
-        self.0.data_type.dimensions()
+    fn dims(&self) -> Vec<Dim> {
+        self.0.data_type.dims()
+    }
+
+    fn kind(&self) -> &'static str {
+        "Variable"
+    }
+
+    fn name(&self) -> String {
+        self.0.name.clone()
+    }
+
+    fn inputs(&self) -> Vec<&dyn ExprNode> {
+        vec![&self.0.initial_value as &dyn ExprNode]
+    }
+
+    fn op(&self) -> Op {
+        Op::Variable(self.0.name.clone())
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter, annotate: bool) -> fmt::Result {
+        describe_leaf(f, &self.0.name, &self.dims(), annotate)
     }
 
     fn make_operation(&self, compiler: &mut Compiler) -> Result<CompiledElement, Status> {
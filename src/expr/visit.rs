@@ -0,0 +1,96 @@
+use super::{Expr, Id, Op};
+use crate::compiler::{CompiledElement, Compiler};
+use crate::data::{Data, Dim};
+use std::collections::HashSet;
+use std::fmt;
+use tensorflow::DataType;
+use tensorflow::Status;
+
+/// A type erased view of an expression, for walking expression trees without knowing the rank
+/// and data type of every node.
+pub trait ExprNode {
+    fn id(&self) -> usize;
+    /// The kind of node, e.g. `BinOpExpr` or `Placeholder`.
+    fn kind(&self) -> &'static str;
+    /// The operation of the node, or the name of a placeholder or variable.
+    fn name(&self) -> String;
+    /// The element type of the node.
+    fn data_type(&self) -> DataType;
+    fn dims(&self) -> Vec<Dim>;
+    fn inputs(&self) -> Vec<&dyn ExprNode>;
+    /// What the node computes from its inputs, for lowering it to a backend.
+    fn op(&self) -> Op;
+    /// Identifies the operation of a pure node apart from its inputs, `None` for placeholders,
+    /// variables and other nodes that must not be shared.
+    fn structural_key(&self) -> Option<String>;
+    /// Writes the node in infix notation, see the `Display` and `Debug` impls of `Expr`.
+    fn describe(&self, f: &mut fmt::Formatter, annotate: bool) -> fmt::Result;
+    /// Builds the TensorFlow operation of the node, used by the `Tensorflow` backend.
+    #[doc(hidden)]
+    fn make_operation(&self, compiler: &mut Compiler) -> Result<CompiledElement, Status>;
+}
+
+impl<const RANK: usize, D: Data<RANK>> ExprNode for Expr<RANK, D> {
+    fn id(&self) -> usize {
+        self.0.id()
+    }
+
+    fn kind(&self) -> &'static str {
+        self.0.kind()
+    }
+
+    fn name(&self) -> String {
+        self.0.name()
+    }
+
+    fn data_type(&self) -> DataType {
+        self.0.data_type().data_type()
+    }
+
+    fn dims(&self) -> Vec<Dim> {
+        self.0.dims()
+    }
+
+    fn inputs(&self) -> Vec<&dyn ExprNode> {
+        self.0.inputs()
+    }
+
+    fn op(&self) -> Op {
+        self.0.op()
+    }
+
+    fn structural_key(&self) -> Option<String> {
+        self.0.structural_key()
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter, annotate: bool) -> fmt::Result {
+        self.0.describe(f, annotate)
+    }
+
+    fn make_operation(&self, compiler: &mut Compiler) -> Result<CompiledElement, Status> {
+        self.0.make_operation(compiler)
+    }
+}
+
+pub trait ExprVisitor {
+    fn visit(&mut self, node: &dyn ExprNode);
+}
+
+/// Visits every node reachable from `root` exactly once, inputs before the nodes using them.
+/// Nodes are identified by their id, so subexpressions shared through `clone` are visited once.
+pub fn walk(root: &dyn ExprNode, visitor: &mut dyn ExprVisitor) {
+    let mut visited = HashSet::new();
+    walk_node(root, visitor, &mut visited);
+}
+
+fn walk_node(node: &dyn ExprNode, visitor: &mut dyn ExprVisitor, visited: &mut HashSet<Id>) {
+    if !visited.insert(node.id()) {
+        return;
+    }
+
+    for input in node.inputs() {
+        walk_node(input, visitor, visited);
+    }
+
+    visitor.visit(node);
+}
@@ -0,0 +1,269 @@
+use crate::compiler::{CompiledElement, Compiler};
+use crate::data::{Data, Dim};
+use crate::expr::{describe_leaf, get_id, Expr, ExprImpl, ExprNode, Id, Op, Placeholder};
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+use tensorflow::ops;
+use tensorflow::Code;
+use tensorflow::ImportGraphDefOptions;
+use tensorflow::Operation;
+use tensorflow::SavedModelBundle;
+use tensorflow::Scope;
+use tensorflow::SessionOptions;
+use tensorflow::Shape;
+use tensorflow::Status;
+
+/// A SavedModel loaded with `Compiler::load_saved_model`. The variables of the model live in
+/// its session, see `RuntimeSession::from_saved_model`.
+pub struct SavedModel {
+    pub(crate) bundle: SavedModelBundle,
+}
+
+impl SavedModel {
+    /// The placeholder behind the input `name` of signature `signature`.
+    pub fn input<const RANK: usize, D: Data<RANK> + From<[Dim; RANK]> + 'static>(
+        &self,
+        compiler: &mut Compiler,
+        signature: &str,
+        name: &str,
+    ) -> Result<Placeholder<RANK, D>, Status> {
+        let output_name = self
+            .bundle
+            .meta_graph_def()
+            .get_signature(signature)?
+            .get_input(name)?
+            .name()
+            .clone();
+
+        if output_name.index != 0 {
+            return Err(Status::new_set_lossy(
+                Code::InvalidArgument,
+                &format!("Input {} is not the first output of an operation", name),
+            ));
+        }
+
+        compiler.imported_placeholder(&output_name.name)
+    }
+
+    /// The expression behind the output `name` of signature `signature`.
+    pub fn output<const RANK: usize, D: Data<RANK> + From<[Dim; RANK]> + 'static>(
+        &self,
+        compiler: &mut Compiler,
+        signature: &str,
+        name: &str,
+    ) -> Result<Expr<RANK, D>, Status> {
+        let output_name = self
+            .bundle
+            .meta_graph_def()
+            .get_signature(signature)?
+            .get_output(name)?
+            .name()
+            .clone();
+
+        compiler.imported_output(&format!("{}:{}", output_name.name, output_name.index))
+    }
+}
+
+impl Compiler {
+    /// Loads a SavedModel into a new compiler. Expressions built on the returned compiler can
+    /// use the inputs and outputs of the model through `SavedModel::input` and
+    /// `SavedModel::output`.
+    pub fn load_saved_model<P: AsRef<Path>>(
+        path: P,
+        tags: &[&str],
+    ) -> Result<(Compiler, SavedModel), Status> {
+        let scope = Scope::new_root_scope();
+        let bundle = SavedModelBundle::load(
+            &SessionOptions::new(),
+            tags,
+            &mut scope.graph_mut(),
+            path,
+        )?;
+
+        Ok((Compiler::new(scope), SavedModel { bundle }))
+    }
+
+    /// Imports a serialized (typically frozen) `GraphDef`. A non-empty `prefix` is prepended to
+    /// the names of all imported operations.
+    pub fn import_graph_def(&mut self, graph_def: &[u8], prefix: &str) -> Result<(), Status> {
+        let mut options = ImportGraphDefOptions::new();
+
+        if !prefix.is_empty() {
+            options.set_prefix(prefix)?;
+        }
+
+        self.backend.scope.graph_mut().import_graph_def(graph_def, &options)
+    }
+
+    /// A placeholder for the imported operation `name`. The data type and rank of the
+    /// operation must match `D` and `RANK`.
+    pub fn imported_placeholder<const RANK: usize, D: Data<RANK> + From<[Dim; RANK]>>(
+        &mut self,
+        name: &str,
+    ) -> Result<Placeholder<RANK, D>, Status> {
+        let operation = self.imported_operation(name)?;
+        let data_type = self.imported_data_type::<RANK, D>(&operation, 0, name)?;
+        let id = get_id();
+
+        self.elements.insert(id, CompiledElement::Operation(operation));
+
+        Ok(Placeholder {
+            id,
+            name: name.to_string(),
+            data_type,
+        })
+    }
+
+    /// An expression for the imported output `name`, either an operation name or
+    /// `operation:index`. The data type and rank of the output must match `D` and `RANK`.
+    pub fn imported_output<const RANK: usize, D: Data<RANK> + From<[Dim; RANK]> + 'static>(
+        &mut self,
+        name: &str,
+    ) -> Result<Expr<RANK, D>, Status> {
+        let (operation_name, index) = split_output_name(name);
+
+        let operation = self.imported_operation(operation_name)?;
+        let data_type = self.imported_data_type::<RANK, D>(&operation, index, name)?;
+        let id = get_id();
+
+        let element = if index == 0 {
+            CompiledElement::Operation(operation)
+        } else {
+            CompiledElement::Operation(ops::identity(
+                operation.output(index),
+                self.borrow_scope_mut(),
+            )?)
+        };
+
+        self.elements.insert(id, element);
+
+        Ok(Expr(Rc::new(ImportedExpr {
+            id,
+            name: name.to_string(),
+            data_type,
+        })))
+    }
+
+    fn imported_operation(&self, name: &str) -> Result<Operation, Status> {
+        self.backend.scope.graph().operation_by_name_required(name)
+    }
+
+    fn imported_data_type<const RANK: usize, D: Data<RANK> + From<[Dim; RANK]>>(
+        &self,
+        operation: &Operation,
+        index: usize,
+        name: &str,
+    ) -> Result<D, Status> {
+        let shape = self.backend.scope.graph().tensor_shape(operation.output(index))?;
+        let dimensions = imported_dimensions::<RANK>(&shape, name)?;
+        let data_type = D::from(dimensions);
+
+        if operation.output_type(index) != data_type.data_type() {
+            return Err(Status::new_set_lossy(
+                Code::InvalidArgument,
+                &format!(
+                    "{} has type {:?}, expected {:?}",
+                    name,
+                    operation.output_type(index),
+                    data_type.data_type()
+                ),
+            ));
+        }
+
+        Ok(data_type)
+    }
+}
+
+/// Splits `operation:index` into its parts. A name without an index refers to output 0.
+fn split_output_name(name: &str) -> (&str, usize) {
+    match name.rsplit_once(':') {
+        Some((operation_name, index)) => match index.parse::<usize>() {
+            Ok(index) => (operation_name, index),
+            Err(_) => (name, 0),
+        },
+        None => (name, 0),
+    }
+}
+
+fn imported_dimensions<const RANK: usize>(shape: &Shape, name: &str) -> Result<[Dim; RANK], Status> {
+    if shape.dims() != Some(RANK) {
+        return Err(Status::new_set_lossy(
+            Code::InvalidArgument,
+            &format!("{} has shape {:?}, expected rank {}", name, shape, RANK),
+        ));
+    }
+
+    let mut dimensions = [Dim::Dynamic; RANK];
+
+    for (i, dimension) in dimensions.iter_mut().enumerate() {
+        if let Some(d) = shape[i] {
+            if d >= 0 {
+                *dimension = Dim::Fixed(d as usize);
+            }
+        }
+    }
+
+    Ok(dimensions)
+}
+
+/// An output of an imported graph. It is registered with the compiler when it is created, so
+/// it is only compiled from scratch when used with another compiler over the same graph.
+pub(crate) struct ImportedExpr<const RANK: usize, D: Data<RANK>> {
+    id: Id,
+    name: String,
+    data_type: D,
+}
+
+impl<const RANK: usize, D: Data<RANK>> ExprImpl<RANK, D> for ImportedExpr<RANK, D> {
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    fn data_type(&self) -> D {
+        self.data_type.clone()
+    }
+
+    fn shape(&self) -> Shape {
+        self.data_type.shape()
+    }
+
+    fn dims(&self) -> Vec<Dim> {
+        self.data_type.dims()
+    }
+
+    fn kind(&self) -> &'static str {
+        "ImportedExpr"
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn inputs(&self) -> Vec<&dyn ExprNode> {
+        Vec::new()
+    }
+
+    fn op(&self) -> Op {
+        Op::Imported(self.name.clone())
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter, annotate: bool) -> fmt::Result {
+        describe_leaf(f, &self.name, &self.dims(), annotate)
+    }
+
+    fn make_operation(&self, compiler: &mut Compiler) -> Result<CompiledElement, Status> {
+        let (operation_name, index) = split_output_name(&self.name);
+
+        let operation = compiler.imported_operation(operation_name)?;
+
+        if index == 0 {
+            Ok(CompiledElement::Operation(operation))
+        } else {
+            Ok(CompiledElement::Operation(ops::identity(
+                operation.output(index),
+                compiler.borrow_scope_mut(),
+            )?))
+        }
+    }
+}
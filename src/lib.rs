@@ -1,10 +1,23 @@
 extern crate tensorflow;
 
+pub mod backend;
+mod checkpoint;
 pub mod compiler;
 pub mod data;
+pub mod dot;
+pub mod error;
+pub mod eval;
 pub mod expr;
+pub mod import;
 pub mod runtime;
+pub mod saved_model;
 pub mod tensordata;
+mod train;
 
+pub use backend::Backend;
 pub use compiler::Compiler;
+pub use error::EvalError;
+pub use error::ShapeError;
+pub use eval::Evaluator;
 pub use runtime::RuntimeSession;
+pub use saved_model::Signature;
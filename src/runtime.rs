@@ -1,33 +1,224 @@
+use crate::checkpoint::Checkpoint;
 use crate::compiler::CompiledElement;
 use crate::compiler::Compiler;
 use crate::data::Data;
+use crate::data::Dim;
+use crate::data::NoData;
 use crate::expr::Expr;
 use crate::expr::Id;
+use crate::expr::Placeholder;
 use crate::expr::PlaceholderRef;
 use crate::expr::Variable;
+use crate::import::SavedModel;
+use crate::saved_model::{Signature, SignatureTensor};
 use crate::tensordata::TaggedTensor;
+use crate::tensordata::TensorData;
 use std::collections::HashMap;
 use std::ops::Deref;
+use std::path::Path;
 use tensorflow::Code;
 use tensorflow::FetchToken;
+use tensorflow::SavedModelBuilder;
+use tensorflow::SavedModelSaver;
+use tensorflow::Scope;
 use tensorflow::Session;
 use tensorflow::SessionOptions;
 use tensorflow::SessionRunArgs;
+use tensorflow::SignatureDef;
 use tensorflow::Status;
+use tensorflow::Tensor;
+use tensorflow::TensorInfo;
+use tensorflow::Variable as TFVariable;
 
 pub struct RuntimeSession {
     elements: HashMap<Id, CompiledElement>,
     session: Session,
+    scope: Scope,
+    variables: Vec<TFVariable>,
+    /// Built on the first `save_checkpoint` or `restore_checkpoint`, so that sessions which
+    /// never checkpoint keep their graph free of save and restore operations.
+    checkpoint: Option<Checkpoint>,
+    /// The saver of the last `export_saved_model`, reused while the tags and signatures stay
+    /// the same.
+    export: Option<SavedModelExport>,
+}
+
+/// A SavedModel saver injected into the graph, with the tags and signatures it exports.
+struct SavedModelExport {
+    tags: Vec<String>,
+    signatures: Vec<Signature>,
+    saver: SavedModelSaver,
 }
 
 impl RuntimeSession {
     pub fn new(compiler: Compiler) -> Result<Self, Status> {
+        Self::with_session(compiler, None)
+    }
+
+    /// A session over a compiler from `Compiler::load_saved_model`, using the session of the
+    /// SavedModel so that its variables keep their loaded values. Checkpoints of such a session
+    /// only hold the variables of the compiled expressions, not those of the SavedModel.
+    pub fn from_saved_model(compiler: Compiler, saved_model: SavedModel) -> Result<Self, Status> {
+        Self::with_session(compiler, Some(saved_model.bundle.session))
+    }
+
+    fn with_session(compiler: Compiler, session: Option<Session>) -> Result<Self, Status> {
         let elements = compiler.elements;
-        let scope = compiler.scope;
-        let session_options = SessionOptions::new();
-        let session = Session::new(&session_options, scope.graph().deref())?;
+        let scope = compiler.backend.scope;
+
+        let mut variables: Vec<TFVariable> = Vec::new();
+
+        for element in elements.values() {
+            match element {
+                CompiledElement::Variable(variable) => variables.push(variable.clone()),
+                CompiledElement::Optimizer(_, slots) => variables.extend_from_slice(slots),
+                CompiledElement::Operation(_) | CompiledElement::Gradients(_) => {}
+            }
+        }
+
+        variables.sort_by(|a, b| a.name().cmp(b.name()));
+
+        let session = match session {
+            Some(session) => session,
+            None => {
+                let session_options = SessionOptions::new();
+                Session::new(&session_options, scope.graph().deref())?
+            }
+        };
+
+        Ok(Self {
+            elements,
+            session,
+            scope,
+            variables,
+            checkpoint: None,
+            export: None,
+        })
+    }
+
+    /// Exports the graph and the current values of all variables as a SavedModel directory
+    /// with the given tags (typically `"serve"`) and signatures. Exporting again with the same
+    /// tags and signatures, e.g. once per epoch, reuses the save operations of the first export;
+    /// other tags or signatures add new ones to the graph.
+    pub fn export_saved_model<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        tags: &[&str],
+        signatures: &[Signature],
+    ) -> Result<(), Status> {
+        let tags: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+
+        let export = match self.export.take() {
+            Some(export) if export.tags == tags && export.signatures == signatures => export,
+            _ => SavedModelExport {
+                saver: self.inject_saved_model_saver(&tags, signatures)?,
+                tags,
+                signatures: signatures.to_vec(),
+            },
+        };
+
+        let result = export.saver.save(&self.session, &self.scope.graph(), path);
+        self.export = Some(export);
+        result
+    }
+
+    fn inject_saved_model_saver(
+        &mut self,
+        tags: &[String],
+        signatures: &[Signature],
+    ) -> Result<SavedModelSaver, Status> {
+        let mut builder = SavedModelBuilder::new();
+
+        builder.add_collection("variables", &self.variables);
+
+        for tag in tags {
+            builder.add_tag(tag);
+        }
+
+        for signature in signatures {
+            let mut signature_def = SignatureDef::new(signature.method_name.clone());
+
+            for input in &signature.inputs {
+                signature_def.add_input_info(input.name.clone(), self.tensor_info(input)?);
+            }
+
+            for output in &signature.outputs {
+                signature_def.add_output_info(output.name.clone(), self.tensor_info(output)?);
+            }
+
+            builder.add_signature(&signature.key, signature_def);
+        }
+
+        builder.inject(&mut self.scope)
+    }
+
+    fn tensor_info(&self, tensor: &SignatureTensor) -> Result<TensorInfo, Status> {
+        match self.elements.get(&tensor.id) {
+            Some(element) => Ok(TensorInfo::new(
+                tensor.data_type,
+                tensor.shape.clone(),
+                element.output()?.name()?,
+            )),
+            None => Err(Status::new_set_lossy(
+                Code::Unknown,
+                &format!("Signature tensor {} isn't compiled", tensor.name),
+            )),
+        }
+    }
+
+    /// Saves the values of all variables, including optimizer slots, to a TensorFlow V2
+    /// checkpoint with the given path prefix. Fails if two variables have the same name, since
+    /// the checkpoint is keyed by name.
+    pub fn save_checkpoint<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Status> {
+        let checkpoint = self.checkpoint()?;
+        let prefix = Self::checkpoint_prefix(path.as_ref())?;
+
+        let mut args = self.session_run_args();
+        args.add_feed(&checkpoint.prefix, 0, &prefix);
+        args.add_target(&checkpoint.save);
 
-        Ok(Self { elements, session })
+        self.run(&mut args)
+    }
+
+    /// Restores the values of all variables from a checkpoint written by `save_checkpoint`.
+    /// This replaces running the initializers.
+    pub fn restore_checkpoint<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Status> {
+        let checkpoint = self.checkpoint()?;
+        let prefix = Self::checkpoint_prefix(path.as_ref())?;
+
+        let mut args = self.session_run_args();
+        args.add_feed(&checkpoint.prefix, 0, &prefix);
+        args.add_target(&checkpoint.restore);
+
+        self.run(&mut args)
+    }
+
+    /// The save and restore operations, added to the graph on first use.
+    fn checkpoint(&mut self) -> Result<Checkpoint, Status> {
+        if self.variables.is_empty() {
+            return Err(Status::new_set_lossy(
+                Code::FailedPrecondition,
+                "There are no variables to checkpoint",
+            ));
+        }
+
+        if let Some(checkpoint) = &self.checkpoint {
+            return Ok(checkpoint.clone());
+        }
+
+        let checkpoint = Checkpoint::build(&mut self.scope, &self.variables)?;
+        self.checkpoint = Some(checkpoint.clone());
+        Ok(checkpoint)
+    }
+
+    fn checkpoint_prefix(path: &Path) -> Result<Tensor<String>, Status> {
+        match path.to_str() {
+            Some(path) => Ok(Tensor::from(path.to_string())),
+            None => Err(Status::new_set_lossy(
+                Code::InvalidArgument,
+                "Checkpoint path is not valid unicode",
+            )),
+        }
     }
 
     pub fn target_initializers(&self, args: &mut SessionRunArgs) -> usize {
@@ -93,6 +284,10 @@ impl RuntimeSession {
                 Code::InvalidArgument,
                 "Cannot fetch output from an optimizer",
             )),
+            Some(CompiledElement::Gradients(_)) => Err(Status::new_set_lossy(
+                Code::InvalidArgument,
+                "Cannot fetch the gradients directly",
+            )),
             None => Err(Status::new_set_lossy(
                 Code::Unknown,
                 "Expression isn't compiled",
@@ -100,6 +295,57 @@ impl RuntimeSession {
         }
     }
 
+    /// Like `request_fetch`, but the returned handle yields a `TensorData` of the expression's
+    /// type instead of an untyped token.
+    pub fn request_typed_fetch<const RANK: usize, D: Data<RANK>>(
+        &self,
+        args: &mut SessionRunArgs,
+        expr: &Expr<RANK, D>,
+    ) -> Result<TypedFetch<RANK, D>, Status> {
+        let token = self.request_fetch(args, expr)?;
+
+        Ok(TypedFetch {
+            token,
+            data_type: expr.0.data_type(),
+        })
+    }
+
+    /// Runs the session once and returns the values of `fetches`, which is an expression
+    /// reference or a tuple of them, e.g.
+    /// `session.eval((&e1, &e2), feeds![input => &[1.0_f32, 2.0]])`.
+    pub fn eval<F: Fetch>(&self, fetches: F, feeds: Vec<Box<dyn Feed>>) -> Result<F::Output, Status> {
+        self.eval_with_targets(&[], fetches, feeds)
+    }
+
+    /// Like `eval`, but also runs `targets`, typically the result of `minimize`.
+    pub fn eval_with_targets<F: Fetch>(
+        &self,
+        targets: &[&Expr<0, NoData>],
+        fetches: F,
+        feeds: Vec<Box<dyn Feed>>,
+    ) -> Result<F::Output, Status> {
+        let prepared = feeds
+            .iter()
+            .map(|feed| feed.prepare())
+            .collect::<Result<Vec<_>, Status>>()?;
+
+        let mut args = self.session_run_args();
+
+        for feed in &prepared {
+            feed.add_to(self, &mut args)?;
+        }
+
+        for target in targets {
+            self.add_target(&mut args, target);
+        }
+
+        let tokens = fetches.request(self, &mut args)?;
+
+        self.run(&mut args)?;
+
+        F::get(tokens, &mut args)
+    }
+
     pub fn session_run_args(&self) -> SessionRunArgs {
         SessionRunArgs::new()
     }
@@ -138,7 +384,7 @@ impl RuntimeSession {
                     "Not a placeholder",
                 ))
             }
-            Some(CompiledElement::Optimizer(_, _)) => {
+            Some(CompiledElement::Optimizer(_, _)) | Some(CompiledElement::Gradients(_)) => {
                 Err(Status::new_set_lossy(
                     Code::InvalidArgument,
                     "Not a placeholder",
@@ -153,3 +399,175 @@ impl RuntimeSession {
         }
     }
 }
+
+/// A fetch request for the value of an `Expr<RANK, D>`, see
+/// `RuntimeSession::request_typed_fetch`.
+pub struct TypedFetch<const RANK: usize, D: Data<RANK>> {
+    token: FetchToken,
+    data_type: D,
+}
+
+impl<const RANK: usize, D: Data<RANK> + 'static> TypedFetch<RANK, D> {
+    /// Gets the fetched value after the session has run. Fails if the dimensions of the result
+    /// differ from those of the expression. Dynamic dimensions take the fetched sizes.
+    pub fn get(self, args: &mut SessionRunArgs) -> Result<TensorData<RANK, D>, Status> {
+        let tensor: Tensor<D::Element> = args.fetch(self.token)?;
+        let expected = self.data_type.dims();
+        let matches = tensor.dims().len() == expected.len()
+            && tensor
+                .dims()
+                .iter()
+                .zip(&expected)
+                .all(|(d, e)| e.is_compatible_with(&Dim::Fixed(*d as usize)));
+
+        if !matches {
+            return Err(Status::new_set_lossy(
+                Code::InvalidArgument,
+                &format!(
+                    "Fetched tensor has dimensions {:?}, expected {:?}",
+                    tensor.dims(),
+                    expected
+                ),
+            ));
+        }
+
+        Ok(TensorData {
+            data: Some(tensor.to_vec()),
+            data_type: self.data_type.with_known_dimensions(tensor.dims()),
+        })
+    }
+
+    pub fn get_tagged(self, args: &mut SessionRunArgs) -> Result<TaggedTensor<RANK, D>, Status> {
+        self.get(args)?.tag()
+    }
+}
+
+/// Something that can be fetched with `RuntimeSession::eval`: a reference to an expression or a
+/// tuple of fetches.
+pub trait Fetch {
+    type Output;
+    type Tokens;
+
+    fn request(
+        &self,
+        session: &RuntimeSession,
+        args: &mut SessionRunArgs,
+    ) -> Result<Self::Tokens, Status>;
+
+    fn get(tokens: Self::Tokens, args: &mut SessionRunArgs) -> Result<Self::Output, Status>;
+}
+
+impl<const RANK: usize, D: Data<RANK> + 'static> Fetch for &Expr<RANK, D> {
+    type Output = TensorData<RANK, D>;
+    type Tokens = TypedFetch<RANK, D>;
+
+    fn request(
+        &self,
+        session: &RuntimeSession,
+        args: &mut SessionRunArgs,
+    ) -> Result<Self::Tokens, Status> {
+        session.request_typed_fetch(args, self)
+    }
+
+    fn get(tokens: Self::Tokens, args: &mut SessionRunArgs) -> Result<Self::Output, Status> {
+        tokens.get(args)
+    }
+}
+
+macro_rules! fetch_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: Fetch),*> Fetch for ($($name,)*) {
+            type Output = ($($name::Output,)*);
+            type Tokens = ($($name::Tokens,)*);
+
+            #[allow(non_snake_case, unused_variables)]
+            fn request(
+                &self,
+                session: &RuntimeSession,
+                args: &mut SessionRunArgs,
+            ) -> Result<Self::Tokens, Status> {
+                let ($($name,)*) = self;
+
+                Ok(($($name.request(session, args)?,)*))
+            }
+
+            #[allow(non_snake_case, unused_variables)]
+            fn get(tokens: Self::Tokens, args: &mut SessionRunArgs) -> Result<Self::Output, Status> {
+                let ($($name,)*) = tokens;
+
+                Ok(($($name::get($name, args)?,)*))
+            }
+        }
+    };
+}
+
+fetch_tuple!();
+fetch_tuple!(A);
+fetch_tuple!(A, B);
+fetch_tuple!(A, B, C);
+fetch_tuple!(A, B, C, E);
+fetch_tuple!(A, B, C, E, F);
+fetch_tuple!(A, B, C, E, F, G);
+
+/// A value for a placeholder, made with `feed_value` or the `feeds!` macro.
+pub trait Feed {
+    fn prepare(&self) -> Result<Box<dyn PreparedFeed>, Status>;
+}
+
+/// A feed whose value has been converted to a tensor.
+pub trait PreparedFeed {
+    fn add_to<'l>(
+        &'l self,
+        session: &RuntimeSession,
+        args: &mut SessionRunArgs<'l>,
+    ) -> Result<(), Status>;
+}
+
+struct FeedValue<const RANK: usize, D: Data<RANK>> {
+    placeholder: PlaceholderRef<RANK, D>,
+    value: TensorData<RANK, D>,
+}
+
+struct PreparedFeedValue<const RANK: usize, D: Data<RANK>> {
+    placeholder: PlaceholderRef<RANK, D>,
+    tensor: TaggedTensor<RANK, D>,
+}
+
+impl<const RANK: usize, D: Data<RANK> + 'static> Feed for FeedValue<RANK, D> {
+    fn prepare(&self) -> Result<Box<dyn PreparedFeed>, Status> {
+        Ok(Box::new(PreparedFeedValue {
+            placeholder: self.placeholder.clone(),
+            tensor: self.value.tag()?,
+        }))
+    }
+}
+
+impl<const RANK: usize, D: Data<RANK> + 'static> PreparedFeed for PreparedFeedValue<RANK, D> {
+    fn add_to<'l>(
+        &'l self,
+        session: &RuntimeSession,
+        args: &mut SessionRunArgs<'l>,
+    ) -> Result<(), Status> {
+        session.add_feed(args, &self.placeholder, &self.tensor)
+    }
+}
+
+pub fn feed_value<const RANK: usize, D: Data<RANK> + 'static, T: Into<TensorData<RANK, D>>>(
+    placeholder: &Placeholder<RANK, D>,
+    value: T,
+) -> Box<dyn Feed> {
+    Box::new(FeedValue {
+        placeholder: placeholder.refer(),
+        value: value.into(),
+    })
+}
+
+/// Builds the feeds for `RuntimeSession::eval`: `feeds![input => input_data, label => 1.0_f32]`.
+#[macro_export]
+macro_rules! feeds {
+    ($($placeholder:expr => $value:expr),* $(,)?) => {{
+        let feeds: Vec<Box<dyn $crate::runtime::Feed>> =
+            vec![$($crate::runtime::feed_value(&$placeholder, $value)),*];
+        feeds
+    }};
+}
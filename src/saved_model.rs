@@ -0,0 +1,73 @@
+use crate::data::Data;
+use crate::expr::Expr;
+use crate::expr::Id;
+use crate::expr::PlaceholderRef;
+use tensorflow::DataType;
+use tensorflow::Shape;
+use tensorflow::DEFAULT_SERVING_SIGNATURE_DEF_KEY;
+use tensorflow::PREDICT_METHOD_NAME;
+
+/// A named signature of an exported SavedModel, mapping names to placeholders (inputs) and
+/// expressions (outputs).
+#[derive(Clone, PartialEq)]
+pub struct Signature {
+    pub(crate) key: String,
+    pub(crate) method_name: String,
+    pub(crate) inputs: Vec<SignatureTensor>,
+    pub(crate) outputs: Vec<SignatureTensor>,
+}
+
+#[derive(Clone, PartialEq)]
+pub(crate) struct SignatureTensor {
+    pub(crate) name: String,
+    pub(crate) id: Id,
+    pub(crate) data_type: DataType,
+    pub(crate) shape: Shape,
+}
+
+impl Signature {
+    /// A signature with the given key and the `tensorflow/serving/predict` method name.
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            method_name: PREDICT_METHOD_NAME.to_string(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// The `serving_default` signature, which is what TF Serving uses when no signature is
+    /// requested.
+    pub fn serving_default() -> Self {
+        Self::new(DEFAULT_SERVING_SIGNATURE_DEF_KEY)
+    }
+
+    pub fn method_name(mut self, method_name: &str) -> Self {
+        self.method_name = method_name.to_string();
+        self
+    }
+
+    pub fn input<const RANK: usize, D: Data<RANK>>(
+        mut self,
+        name: &str,
+        placeholder: &PlaceholderRef<RANK, D>,
+    ) -> Self {
+        self.inputs.push(SignatureTensor {
+            name: name.to_string(),
+            id: placeholder.id,
+            data_type: placeholder.data_type.data_type(),
+            shape: placeholder.data_type.shape(),
+        });
+        self
+    }
+
+    pub fn output<const RANK: usize, D: Data<RANK>>(mut self, name: &str, expr: &Expr<RANK, D>) -> Self {
+        self.outputs.push(SignatureTensor {
+            name: name.to_string(),
+            id: expr.0.id(),
+            data_type: expr.0.data_type().data_type(),
+            shape: expr.0.shape(),
+        });
+        self
+    }
+}
@@ -1,4 +1,6 @@
 use crate::data;
+use crate::data::Dim;
+use crate::error::ShapeError;
 use tensorflow::Status;
 use tensorflow::Tensor;
 
@@ -25,18 +27,37 @@ impl<const RANK: usize, D: data::Data<RANK> + 'static> TryFrom<&TensorData<RANK,
 }
 
 impl<const RANK: usize, D: data::Data<RANK> + 'static> TensorData<RANK, D> {
+    /// A tensor with the given values in row-major order. Panics unless the dimensions are
+    /// fully defined and hold exactly `values.len()` values, see `try_new`.
     pub fn new<S: Into<D>>(shape: S, values: &[D::Element]) -> Self {
+        Self::try_new(shape, values).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new<S: Into<D>>(shape: S, values: &[D::Element]) -> Result<Self, ShapeError> {
         let data_type = shape.into();
+        let dims = data_type.dims();
+        let count: Option<usize> = dims.iter().map(|d| d.size()).product();
+
+        if count != Some(values.len()) {
+            return Err(ShapeError::new("tensor_data", dims, vec![Dim::Fixed(values.len())]));
+        }
+
         let mut data = Vec::new();
         data.extend_from_slice(values);
-        TensorData {
+        Ok(TensorData {
             data: Some(data),
             data_type,
-        }
+        })
     }
 
+    /// A tensor of zeros. Panics unless the dimensions are fully defined.
     pub fn new_with_zero<S: Into<D>>(shape: S) -> Self {
         let data_type = shape.into();
+
+        if !data_type.is_fully_defined() {
+            panic!("{}", ShapeError::new("tensor_data", data_type.dims(), Vec::new()));
+        }
+
         TensorData {
             data: None,
             data_type,
@@ -53,22 +74,52 @@ impl<const RANK: usize, D: data::Data<RANK> + 'static> TensorData<RANK, D> {
     pub fn tag(&self) -> Result<TaggedTensor<RANK, D>, Status> {
         TaggedTensor::try_from(self)
     }
-}
 
-impl<const D: usize> From<&[f32; D]> for TensorData<1, data::FloatData<1>> {
-    fn from(values: &[f32; D]) -> Self {
-        TensorData::new([D], values)
+    pub fn data_type(&self) -> &D {
+        &self.data_type
     }
-}
 
-impl From<&[f32]> for TensorData<1, data::FloatData<1>> {
-    fn from(values: &[f32]) -> Self {
-        TensorData::new([values.len()], values)
+    pub fn dimensions(&self) -> Vec<u64> {
+        self.data_type.dimensions()
     }
-}
 
-impl From<f32> for TensorData<0, data::FloatData<0>> {
-    fn from(value: f32) -> Self {
-        TensorData::new::<[usize; 0]>([], &[value])
+    /// The values in row-major order. A tensor created with `new_with_zero` yields zeros.
+    pub fn values(&self) -> Vec<D::Element> {
+        match &self.data {
+            None => {
+                let count: u64 = self.data_type.dimensions().iter().product();
+                vec![D::Element::default(); count as usize]
+            }
+            Some(values) => values.clone(),
+        }
     }
 }
+
+macro_rules! tensor_data_from {
+    ($element:ty, $data:ident) => {
+        impl<const D: usize> From<&[$element; D]> for TensorData<1, data::$data<1>> {
+            fn from(values: &[$element; D]) -> Self {
+                TensorData::new([D], values)
+            }
+        }
+
+        impl From<&[$element]> for TensorData<1, data::$data<1>> {
+            fn from(values: &[$element]) -> Self {
+                TensorData::new([values.len()], values)
+            }
+        }
+
+        impl From<$element> for TensorData<0, data::$data<0>> {
+            fn from(value: $element) -> Self {
+                TensorData::new::<[usize; 0]>([], &[value])
+            }
+        }
+    };
+}
+
+tensor_data_from!(f32, FloatData);
+tensor_data_from!(f64, DoubleData);
+tensor_data_from!(i32, Int32Data);
+tensor_data_from!(i64, Int64Data);
+tensor_data_from!(u8, UInt8Data);
+tensor_data_from!(bool, BoolData);
@@ -0,0 +1,178 @@
+use tensorflow::ops;
+use tensorflow::train::ApplyGradientsOptions;
+use tensorflow::train::Optimizer;
+use tensorflow::Code;
+use tensorflow::DataType;
+use tensorflow::Operation;
+use tensorflow::Output;
+use tensorflow::Scope;
+use tensorflow::Status;
+use tensorflow::Variable;
+
+/// Gradient descent with momentum, optionally using Nesterov momentum.
+pub struct MomentumOptimizer {
+    learning_rate: Output,
+    momentum: Output,
+    use_nesterov: bool,
+}
+
+impl MomentumOptimizer {
+    pub fn new<L: Into<Output>, M: Into<Output>>(learning_rate: L, momentum: M) -> Self {
+        Self {
+            learning_rate: learning_rate.into(),
+            momentum: momentum.into(),
+            use_nesterov: false,
+        }
+    }
+
+    pub fn set_use_nesterov(&mut self, use_nesterov: bool) {
+        self.use_nesterov = use_nesterov;
+    }
+}
+
+impl Optimizer for MomentumOptimizer {
+    fn apply_gradients(
+        &self,
+        scope: &mut Scope,
+        opts: ApplyGradientsOptions,
+    ) -> Result<(Vec<Variable>, Operation), Status> {
+        let mut apply_ops = Vec::new();
+        let mut slots = Vec::new();
+
+        for (grad, var) in opts.grads_and_vars {
+            if let Some(grad) = grad {
+                let accum = create_zeros_slot(scope, var, "momentum")?;
+
+                apply_ops.push(
+                    ops::ApplyMomentum::new()
+                        .use_nesterov(self.use_nesterov)
+                        .build(
+                            var.output().clone(),
+                            accum.output().clone(),
+                            self.learning_rate.clone(),
+                            grad.clone(),
+                            self.momentum.clone(),
+                            scope,
+                        )?,
+                );
+
+                slots.push(accum);
+            }
+        }
+
+        Ok((slots, group(apply_ops, scope)?))
+    }
+}
+
+/// RMSProp, as described in Hinton's Coursera lecture 6e.
+pub struct RmsPropOptimizer {
+    learning_rate: Output,
+    decay: Output,
+    momentum: Output,
+    epsilon: Output,
+}
+
+impl RmsPropOptimizer {
+    pub fn new<L: Into<Output>, R: Into<Output>, M: Into<Output>, E: Into<Output>>(
+        learning_rate: L,
+        decay: R,
+        momentum: M,
+        epsilon: E,
+    ) -> Self {
+        Self {
+            learning_rate: learning_rate.into(),
+            decay: decay.into(),
+            momentum: momentum.into(),
+            epsilon: epsilon.into(),
+        }
+    }
+}
+
+impl Optimizer for RmsPropOptimizer {
+    fn apply_gradients(
+        &self,
+        scope: &mut Scope,
+        opts: ApplyGradientsOptions,
+    ) -> Result<(Vec<Variable>, Operation), Status> {
+        let mut apply_ops = Vec::new();
+        let mut slots = Vec::new();
+
+        for (grad, var) in opts.grads_and_vars {
+            if let Some(grad) = grad {
+                let ms = create_zeros_slot(scope, var, "rms")?;
+                let mom = create_zeros_slot(scope, var, "momentum")?;
+
+                apply_ops.push(ops::ApplyRMSProp::new().build(
+                    var.output().clone(),
+                    ms.output().clone(),
+                    mom.output().clone(),
+                    self.learning_rate.clone(),
+                    self.decay.clone(),
+                    self.momentum.clone(),
+                    self.epsilon.clone(),
+                    grad.clone(),
+                    scope,
+                )?);
+
+                slots.push(ms);
+                slots.push(mom);
+            }
+        }
+
+        Ok((slots, group(apply_ops, scope)?))
+    }
+}
+
+/// Makes a scalar constant of a floating point data type.
+pub(crate) fn scalar_constant(
+    value: f64,
+    data_type: DataType,
+    scope: &mut Scope,
+) -> Result<Operation, Status> {
+    match data_type {
+        DataType::Float => ops::constant(value as f32, scope),
+        DataType::Double => ops::constant(value, scope),
+        _ => Err(Status::new_set_lossy(
+            Code::InvalidArgument,
+            "Only float and double constants are supported",
+        )),
+    }
+}
+
+/// Creates a zero initialized variable with the same type and shape as `primary`. The value is
+/// built from the shape rather than from `primary` itself, so that the initializers can run in
+/// any order, which requires the shape of `primary` to be fully known.
+fn create_zeros_slot(scope: &mut Scope, primary: &Variable, name: &str) -> Result<Variable, Status> {
+    let shape = primary.shape().clone();
+    let dimensions: Option<Vec<i64>> = shape
+        .dims()
+        .and_then(|rank| (0 .. rank).map(|i| shape[i]).collect());
+
+    let dimensions = dimensions.ok_or_else(|| {
+        Status::new_set_lossy(
+            Code::InvalidArgument,
+            &format!("The shape of variable {} is not fully known", primary.name()),
+        )
+    })?;
+
+    let dimensions = ops::constant(&dimensions[..], scope)?;
+    let zero = scalar_constant(0.0, primary.data_type(), scope)?;
+    let zeros = ops::fill(dimensions, zero, scope)?;
+
+    Variable::builder()
+        .initial_value(zeros)
+        .data_type(primary.data_type())
+        .shape(shape)
+        .build(&mut scope.with_op_name(&format!("{}/{}", primary.name(), name)))
+}
+
+/// Groups a list of operations under a single no-op target.
+fn group(operations: Vec<Operation>, scope: &mut Scope) -> Result<Operation, Status> {
+    let mut no_op = ops::NoOp::new();
+
+    for operation in operations {
+        no_op = no_op.add_control_input(operation);
+    }
+
+    no_op.build(scope)
+}